@@ -2,8 +2,13 @@ use clap::Parser;
 use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
 
-use mpc_websocket::{Result, Server};
+use mpc_websocket::{
+    relay::RedisRelay, storage::FileStorage, ClientAuth, Heartbeat, Limits, Result, Server,
+    ServerOptions, TlsOptions,
+};
 
 #[derive(Debug, Parser)]
 #[clap(
@@ -17,6 +22,66 @@ struct Options {
     /// Path to static files to serve
     #[structopt(parse(from_os_str))]
     files: Option<PathBuf>,
+    /// PEM encoded certificate chain to enable TLS.
+    #[structopt(long, parse(from_os_str), requires = "tls-key")]
+    tls_cert: Option<PathBuf>,
+    /// PEM encoded private key to enable TLS.
+    #[structopt(long, parse(from_os_str), requires = "tls-cert")]
+    tls_key: Option<PathBuf>,
+    /// PEM encoded trust anchors to verify client certificates.
+    #[structopt(long, parse(from_os_str), requires = "tls-cert")]
+    tls_client_ca: Option<PathBuf>,
+    /// Require clients to present a certificate.
+    #[structopt(long, requires = "tls-client-ca")]
+    tls_client_auth_required: bool,
+    /// Export Prometheus metrics from the /metrics route.
+    #[structopt(long)]
+    metrics: bool,
+    /// Seconds to wait for active sessions when shutting down.
+    #[structopt(long)]
+    shutdown_timeout: Option<u64>,
+    /// Directory used to store groups and sessions across restarts.
+    #[structopt(long, parse(from_os_str))]
+    storage: Option<PathBuf>,
+    /// Address of a Redis server used to relay messages between instances.
+    #[structopt(long)]
+    relay_redis: Option<String>,
+    /// Redis channel used to relay messages between instances.
+    #[structopt(long, default_value = "mpc-websocket", requires = "relay-redis")]
+    relay_channel: String,
+    /// Maximum size of a websocket frame in bytes.
+    #[structopt(long)]
+    max_frame_size: Option<usize>,
+    /// Maximum size of a JSON-RPC request in bytes.
+    #[structopt(long)]
+    max_request_size: Option<usize>,
+    /// Maximum number of requests per second for a connection.
+    #[structopt(long)]
+    requests_per_second: Option<u32>,
+    /// Maximum number of groups a connection may belong to.
+    #[structopt(long)]
+    max_groups_per_connection: Option<usize>,
+    /// Maximum number of sessions in a group.
+    #[structopt(long)]
+    max_sessions_per_group: Option<usize>,
+    /// Maximum number of concurrent connections from an IP address.
+    #[structopt(long)]
+    max_connections_per_ip: Option<usize>,
+    /// Seconds between pings sent to each connection, zero to disable.
+    #[structopt(long)]
+    ping_interval: Option<u64>,
+    /// Seconds a connection may be idle before it is closed, zero to disable.
+    #[structopt(long)]
+    idle_timeout: Option<u64>,
+}
+
+/// Convert seconds to a duration where zero is no duration.
+fn seconds(secs: u64) -> Option<Duration> {
+    if secs == 0 {
+        None
+    } else {
+        Some(Duration::from_secs(secs))
+    }
 }
 
 #[tokio::main]
@@ -40,5 +105,49 @@ async fn main() -> Result<()> {
         static_files
     };
 
-    Server::start("mpc", (addr.ip(), addr.port()), static_files).await
+    let limits = Limits {
+        max_frame_size: opts.max_frame_size,
+        max_request_size: opts.max_request_size,
+        requests_per_second: opts.requests_per_second,
+        max_groups_per_connection: opts.max_groups_per_connection,
+        max_sessions_per_group: opts.max_sessions_per_group,
+        max_connections_per_ip: opts.max_connections_per_ip,
+        ..Default::default()
+    };
+
+    let mut heartbeat = Heartbeat::default();
+    if let Some(interval) = opts.ping_interval {
+        heartbeat.interval = seconds(interval);
+    }
+    if let Some(timeout) = opts.idle_timeout {
+        heartbeat.timeout = seconds(timeout);
+    }
+
+    let mut options = ServerOptions::new("mpc")
+        .static_files(static_files)
+        .metrics(opts.metrics)
+        .limits(limits)
+        .heartbeat(heartbeat);
+    if let (Some(cert), Some(key)) = (opts.tls_cert, opts.tls_key) {
+        let mut tls = TlsOptions::new(cert, key);
+        if let Some(client_ca) = opts.tls_client_ca {
+            tls = tls.client_auth(if opts.tls_client_auth_required {
+                ClientAuth::Required(client_ca)
+            } else {
+                ClientAuth::Optional(client_ca)
+            });
+        }
+        options = options.tls(tls);
+    }
+    if let Some(timeout) = opts.shutdown_timeout {
+        options = options.shutdown_timeout(Duration::from_secs(timeout));
+    }
+    if let Some(dir) = opts.storage {
+        options = options.storage(Arc::new(FileStorage::new(dir)?));
+    }
+    if let Some(addr) = opts.relay_redis {
+        options = options.relay(Arc::new(RedisRelay::new(addr, opts.relay_channel)));
+    }
+
+    Server::new(options)?.run((addr.ip(), addr.port())).await
 }
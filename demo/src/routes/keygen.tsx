@@ -19,6 +19,7 @@ import {
   KeyShare,
   Session,
   SessionKind,
+  SessionReady,
   EcdsaWorker,
   WebSocketStream,
   WebSocketSink,
@@ -114,7 +115,7 @@ class Keygen extends Component<KeygenProps, KeygenStateProps> {
     });
 
     // All parties signed up to key generation
-    websocket.on("sessionSignup", async (sessionId: string) => {
+    websocket.on("sessionSignup", async ({ sessionId }: SessionReady) => {
       if (sessionId === this.state.session.uuid) {
         const { runningSession } = this.state;
 
@@ -180,7 +181,7 @@ class Keygen extends Component<KeygenProps, KeygenStateProps> {
       }
     });
 
-    websocket.on("sessionLoad", async (sessionId: string) => {
+    websocket.on("sessionLoad", async ({ sessionId }: SessionReady) => {
       if (sessionId === this.state.session.uuid) {
         const [publicAddress, ,] = this.state.loadedKeyShare;
         const keyShare = findKeyValue(
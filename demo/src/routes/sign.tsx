@@ -14,6 +14,7 @@ import {
   WebSocketStream,
   WebSocketSink,
   SessionKind,
+  SessionReady,
   KeyShare,
   SignResult,
   EcdsaWorker,
@@ -128,7 +129,7 @@ const Proposal = ({
       session.uuid
     );
 
-    websocket.once("sessionSignup", async (sessionId: string) => {
+    websocket.once("sessionSignup", async ({ sessionId }: SessionReady) => {
       if (sessionId === session.uuid) {
         // Keep this to check we don't regress on #49
         if (runningSession) {
@@ -0,0 +1,157 @@
+//! Measure the throughput of relaying messages for many
+//! simultaneous sessions.
+//!
+//! Run with `cargo bench`; the number of sessions and the number
+//! of messages sent by each party can be changed using the
+//! `SESSIONS` and `MESSAGES` environment variables.
+use std::sync::Arc;
+use std::time::Instant;
+
+use mpc_websocket::{services::*, Server, ServerOptions};
+use serde_json::{json, Value};
+use tokio::sync::Barrier;
+use warp::test::WsClient;
+
+fn env(name: &str, default: usize) -> usize {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+async fn recv(client: &mut WsClient) -> Value {
+    let message = client.recv().await.unwrap();
+    serde_json::from_str(message.to_str().unwrap()).unwrap()
+}
+
+async fn call(client: &mut WsClient, method: &str, params: Value) -> Value {
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": method,
+        "params": params,
+    });
+    client.send_text(request.to_string()).await;
+    loop {
+        let message = recv(client).await;
+        if message["id"] == 1 {
+            return message["result"].clone();
+        }
+    }
+}
+
+/// Create a group and key generation session for two parties.
+async fn session(server: &Server) -> (WsClient, WsClient, Value, Value) {
+    let mut alice = warp::test::ws()
+        .path("/mpc")
+        .handshake(server.routes())
+        .await
+        .unwrap();
+    let mut bob = warp::test::ws()
+        .path("/mpc")
+        .handshake(server.routes())
+        .await
+        .unwrap();
+
+    let group_id = call(
+        &mut alice,
+        GROUP_CREATE,
+        json!(["bench", {"parties": 2, "threshold": 1}]),
+    )
+    .await;
+    call(&mut bob, GROUP_JOIN, json!(group_id)).await;
+    let session = call(
+        &mut alice,
+        SESSION_CREATE,
+        json!([group_id, "keygen", null]),
+    )
+    .await;
+    let session_id = session["uuid"].clone();
+    let params = json!([group_id, session_id, "keygen"]);
+    call(&mut alice, SESSION_SIGNUP, params.clone()).await;
+    call(&mut bob, SESSION_SIGNUP, params).await;
+    (alice, bob, group_id, session_id)
+}
+
+/// Send `messages` broadcast messages and wait to receive
+/// the same number of messages from the other party.
+async fn exchange(
+    mut client: WsClient,
+    sender: u16,
+    group_id: Value,
+    session_id: Value,
+    messages: usize,
+    barrier: Arc<Barrier>,
+) {
+    barrier.wait().await;
+    for round in 0..messages {
+        let message = json!({
+            "round": round,
+            "sender": sender,
+            "receiver": null,
+            "uuid": session_id,
+            "body": null,
+        });
+        let request = json!({
+            "jsonrpc": "2.0",
+            "method": SESSION_MESSAGE,
+            "params": [group_id, session_id, "keygen", message],
+        });
+        client.send_text(request.to_string()).await;
+    }
+
+    let mut received = 0;
+    while received < messages {
+        let message = recv(&mut client).await;
+        if message["result"][0] == SESSION_MESSAGE_EVENT {
+            received += 1;
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let sessions = env("SESSIONS", 200);
+    let messages = env("MESSAGES", 100);
+
+    let options = ServerOptions::default().tracing(false);
+    let server = Server::new(options).unwrap();
+
+    let barrier = Arc::new(Barrier::new(sessions * 2 + 1));
+    let mut tasks = Vec::new();
+    for _ in 0..sessions {
+        let (alice, bob, group_id, session_id) = session(&server).await;
+        tasks.push(tokio::task::spawn(exchange(
+            alice,
+            1,
+            group_id.clone(),
+            session_id.clone(),
+            messages,
+            Arc::clone(&barrier),
+        )));
+        tasks.push(tokio::task::spawn(exchange(
+            bob,
+            2,
+            group_id,
+            session_id,
+            messages,
+            Arc::clone(&barrier),
+        )));
+    }
+
+    barrier.wait().await;
+    let start = Instant::now();
+    for task in tasks {
+        task.await.unwrap();
+    }
+    let elapsed = start.elapsed();
+
+    let total = sessions * 2 * messages;
+    println!(
+        "relayed {} messages for {} sessions in {:?} ({:.0} messages/sec)",
+        total,
+        sessions,
+        elapsed,
+        total as f64 / elapsed.as_secs_f64()
+    );
+}
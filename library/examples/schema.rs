@@ -0,0 +1,23 @@
+//! Export the JSON Schema files for the events and
+//! the server information to the `schema` directory.
+//!
+//! Run with `cargo run --example schema` after changing
+//! an event payload.
+use std::path::PathBuf;
+
+use mpc_websocket::services::{Event, ServerInfo};
+use schemars::schema_for;
+
+fn main() -> std::io::Result<()> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("schema");
+    std::fs::create_dir_all(&dir)?;
+    let schemas = [
+        ("event.json", schema_for!(Event)),
+        ("server-info.json", schema_for!(ServerInfo)),
+    ];
+    for (name, schema) in schemas {
+        let json = serde_json::to_string_pretty(&schema).unwrap();
+        std::fs::write(dir.join(name), format!("{}\n", json))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,132 @@
+//! Authentication of connections using signed challenges.
+//!
+//! When a client connects the server emits a `connectionChallenge`
+//! event with a statement naming the server and a random nonce;
+//! the client signs the challenge using Ethereum `personal_sign`
+//! and sends the signature to the `Connection.authenticate` method.
+//! The server recovers the signing address and attaches it to the
+//! connection.
+use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+use rand::RngCore;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use sha3::{Digest, Keccak256};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Error generated authenticating a connection.
+#[derive(Debug, Error)]
+pub enum AuthError {
+    /// Error generated when an address is not valid.
+    #[error("invalid address {0}")]
+    InvalidAddress(String),
+
+    /// Error generated when a signature is not valid.
+    #[error("invalid signature")]
+    InvalidSignature,
+}
+
+/// Ethereum address used to identify authenticated connections.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Address([u8; 20]);
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{}", hex::encode(self.0))
+    }
+}
+
+impl FromStr for Address {
+    type Err = AuthError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value = s.strip_prefix("0x").unwrap_or(s);
+        let bytes = hex::decode(value)
+            .map_err(|_| AuthError::InvalidAddress(s.to_owned()))?;
+        let bytes: [u8; 20] = bytes
+            .try_into()
+            .map_err(|_| AuthError::InvalidAddress(s.to_owned()))?;
+        Ok(Self(bytes))
+    }
+}
+
+impl Serialize for Address {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Address {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(de::Error::custom)
+    }
+}
+
+/// Generate a challenge for a connection to the server at `origin`.
+///
+/// The statement lets the user see which server they are signing
+/// in to and the random nonce prevents the signature being reused.
+pub(crate) fn challenge(origin: &str) -> String {
+    let mut nonce = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    format!(
+        "Sign in to {} to authenticate this connection.\n\nNonce: {}",
+        origin,
+        hex::encode(nonce)
+    )
+}
+
+/// Recover the address that signed `message` using `personal_sign`.
+///
+/// The signature is the hex encoded 65 byte `r || s || v` signature.
+pub fn recover_address(
+    message: &str,
+    signature: &str,
+) -> Result<Address, AuthError> {
+    let value = signature.strip_prefix("0x").unwrap_or(signature);
+    let bytes = hex::decode(value).map_err(|_| AuthError::InvalidSignature)?;
+    if bytes.len() != 65 {
+        return Err(AuthError::InvalidSignature);
+    }
+
+    let signature = Signature::from_slice(&bytes[0..64])
+        .map_err(|_| AuthError::InvalidSignature)?;
+    let v = bytes[64];
+    let v = if v >= 27 { v - 27 } else { v };
+    let recovery_id =
+        RecoveryId::from_byte(v).ok_or(AuthError::InvalidSignature)?;
+
+    // Signatures with a high `s` value must be normalized
+    // which flips the parity of the recovery identifier
+    let (signature, recovery_id) = match signature.normalize_s() {
+        Some(normalized) => (
+            normalized,
+            RecoveryId::new(
+                !recovery_id.is_y_odd(),
+                recovery_id.is_x_reduced(),
+            ),
+        ),
+        None => (signature, recovery_id),
+    };
+
+    let digest = Keccak256::new_with_prefix(format!(
+        "\x19Ethereum Signed Message:\n{}{}",
+        message.len(),
+        message
+    ));
+    let key =
+        VerifyingKey::recover_from_digest(digest, &signature, recovery_id)
+            .map_err(|_| AuthError::InvalidSignature)?;
+
+    let point = key.to_encoded_point(false);
+    let hash = Keccak256::digest(&point.as_bytes()[1..]);
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&hash[12..]);
+    Ok(Address(address))
+}
@@ -21,7 +21,14 @@
 //! The associated session data is typically used by signing sessions
 //! to indicate the message or transaction that will be signed.
 #![deny(missing_docs)]
+pub mod auth;
+mod metrics;
+mod options;
+pub mod relay;
 mod server;
 pub mod services;
+pub mod storage;
 
+pub use metrics::Metrics;
+pub use options::*;
 pub use server::*;
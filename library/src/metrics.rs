@@ -0,0 +1,204 @@
+//! Metrics exported in the Prometheus text format.
+//!
+//! Only counts and timings are recorded; message bodies and
+//! session values are never inspected.
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::State;
+
+/// Upper bounds in seconds for the session duration histogram.
+const DURATION_BUCKETS: [f64; 11] = [
+    0.1, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 600.0,
+];
+
+/// Histogram of observed durations.
+#[derive(Debug, Default)]
+struct Histogram {
+    buckets: [u64; DURATION_BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        for (bucket, bound) in self.buckets.iter_mut().zip(DURATION_BUCKETS) {
+            if value <= bound {
+                *bucket += 1;
+            }
+        }
+        self.count += 1;
+        self.sum += value;
+    }
+}
+
+/// Counters collected by the server.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    requests: Mutex<BTreeMap<&'static str, u64>>,
+    errors: Mutex<BTreeMap<&'static str, u64>>,
+    sessions: Mutex<BTreeMap<String, Histogram>>,
+}
+
+impl Metrics {
+    /// Record a request for a method.
+    pub(crate) fn request(&self, method: &'static str) {
+        *self.requests.lock().unwrap().entry(method).or_default() += 1;
+    }
+
+    /// Record an error returned by a method.
+    pub(crate) fn error(&self, name: &'static str) {
+        *self.errors.lock().unwrap().entry(name).or_default() += 1;
+    }
+
+    /// Record the time taken for a session to be finished by all parties.
+    pub(crate) fn session_finished(&self, kind: String, duration: Duration) {
+        self.sessions
+            .lock()
+            .unwrap()
+            .entry(kind)
+            .or_default()
+            .observe(duration.as_secs_f64());
+    }
+
+    /// Encode the metrics and the current state of the
+    /// server in the Prometheus text format.
+    pub async fn encode(&self, state: &State) -> String {
+        let mut out = String::new();
+
+        gauge(
+            &mut out,
+            "mpc_clients",
+            "Number of connected clients.",
+            &[(None, state.clients.len() as f64)],
+        );
+        gauge(
+            &mut out,
+            "mpc_groups",
+            "Number of groups.",
+            &[(None, state.groups.len() as f64)],
+        );
+
+        let mut sessions: BTreeMap<String, usize> = BTreeMap::new();
+        for group in state.groups.all() {
+            for session in group.lock().await.sessions.values() {
+                *sessions.entry(session.kind.to_string()).or_default() += 1;
+            }
+        }
+        let sessions = sessions
+            .iter()
+            .map(|(kind, count)| (Some(("kind", kind.as_str())), *count as f64))
+            .collect::<Vec<_>>();
+        gauge(&mut out, "mpc_sessions", "Number of sessions.", &sessions);
+
+        let queue = state.queue_metrics();
+        gauge(
+            &mut out,
+            "mpc_send_queue_messages",
+            "Number of messages queued for all clients.",
+            &[(None, queue.queued as f64)],
+        );
+        gauge(
+            &mut out,
+            "mpc_send_queue_max_depth",
+            "Largest number of messages queued for a single client.",
+            &[(None, queue.max_depth as f64)],
+        );
+        counter(
+            &mut out,
+            "mpc_evicted_clients_total",
+            "Clients disconnected because the send queue was full.",
+            &[(None, queue.evicted)],
+        );
+
+        let requests = self.requests.lock().unwrap();
+        let requests = requests
+            .iter()
+            .map(|(method, count)| (Some(("method", *method)), *count))
+            .collect::<Vec<_>>();
+        counter(
+            &mut out,
+            "mpc_requests_total",
+            "Requests received by method.",
+            &requests,
+        );
+
+        let errors = self.errors.lock().unwrap();
+        let errors = errors
+            .iter()
+            .map(|(error, count)| (Some(("error", *error)), *count))
+            .collect::<Vec<_>>();
+        counter(
+            &mut out,
+            "mpc_errors_total",
+            "Errors returned by error type.",
+            &errors,
+        );
+
+        let name = "mpc_session_duration_seconds";
+        let _ = writeln!(
+            out,
+            "# HELP {} Time for sessions to be finished by all parties.",
+            name
+        );
+        let _ = writeln!(out, "# TYPE {} histogram", name);
+        for (kind, histogram) in self.sessions.lock().unwrap().iter() {
+            for (count, bound) in histogram.buckets.iter().zip(DURATION_BUCKETS)
+            {
+                let _ = writeln!(
+                    out,
+                    "{}_bucket{{kind=\"{}\",le=\"{}\"}} {}",
+                    name, kind, bound, count
+                );
+            }
+            let _ = writeln!(
+                out,
+                "{}_bucket{{kind=\"{}\",le=\"+Inf\"}} {}",
+                name, kind, histogram.count
+            );
+            let _ = writeln!(
+                out,
+                "{}_sum{{kind=\"{}\"}} {}",
+                name, kind, histogram.sum
+            );
+            let _ = writeln!(
+                out,
+                "{}_count{{kind=\"{}\"}} {}",
+                name, kind, histogram.count
+            );
+        }
+
+        out
+    }
+}
+
+type Sample<'a, T> = (Option<(&'a str, &'a str)>, T);
+
+fn gauge(out: &mut String, name: &str, help: &str, samples: &[Sample<f64>]) {
+    metric(out, name, help, "gauge", samples);
+}
+
+fn counter(out: &mut String, name: &str, help: &str, samples: &[Sample<u64>]) {
+    metric(out, name, help, "counter", samples);
+}
+
+fn metric<T: std::fmt::Display>(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    kind: &str,
+    samples: &[Sample<T>],
+) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+    for (label, value) in samples {
+        if let Some((key, label)) = label {
+            let _ =
+                writeln!(out, "{}{{{}=\"{}\"}} {}", name, key, label, value);
+        } else {
+            let _ = writeln!(out, "{} {}", name, value);
+        }
+    }
+}
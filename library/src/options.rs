@@ -0,0 +1,319 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use warp::http::header::{HeaderMap, HeaderName, HeaderValue};
+
+use crate::relay::RelayBackend;
+use crate::storage::{MemoryStorage, Storage};
+use crate::{RESUME_GRACE_PERIOD, SEND_QUEUE_CAPACITY, SHUTDOWN_TIMEOUT};
+
+/// Limits for websocket connections and requests.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Limits {
+    /// Maximum size of an incoming websocket message in bytes.
+    pub max_message_size: Option<usize>,
+    /// Maximum size of an incoming websocket frame in bytes.
+    pub max_frame_size: Option<usize>,
+    /// Maximum number of outgoing messages queued for a connection.
+    ///
+    /// Clients that fall so far behind that the queue is full
+    /// are disconnected.
+    pub send_queue_capacity: usize,
+    /// Maximum size of a JSON-RPC request in bytes.
+    pub max_request_size: Option<usize>,
+    /// Maximum number of requests per second for a connection.
+    ///
+    /// Bursts of up to this number of requests are allowed; every
+    /// message counts towards the limit including those that are
+    /// not valid JSON-RPC requests.
+    pub requests_per_second: Option<u32>,
+    /// Maximum number of groups a connection may belong to.
+    pub max_groups_per_connection: Option<usize>,
+    /// Maximum number of sessions in a group.
+    pub max_sessions_per_group: Option<usize>,
+    /// Maximum number of concurrent connections from an IP address.
+    pub max_connections_per_ip: Option<usize>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_message_size: None,
+            max_frame_size: None,
+            send_queue_capacity: SEND_QUEUE_CAPACITY,
+            max_request_size: None,
+            requests_per_second: None,
+            max_groups_per_connection: None,
+            max_sessions_per_group: None,
+            max_connections_per_ip: None,
+        }
+    }
+}
+
+/// Time to live for groups and sessions.
+///
+/// A duration of `None` means the entries never expire.
+#[derive(Debug, Clone)]
+pub struct Expiry {
+    /// Duration a group may be idle before it is removed.
+    ///
+    /// Groups with unfinished sessions or connected clients
+    /// are not removed; clients connected to other server instances
+    /// sharing a relay are treated as connected.
+    pub group: Option<Duration>,
+    /// Duration an unfinished session may exist before it is removed.
+    pub session: Option<Duration>,
+    /// Duration a finished session is retained before it is removed.
+    pub finished_session: Option<Duration>,
+    /// Interval between checks for expired groups and sessions.
+    pub interval: Duration,
+}
+
+impl Default for Expiry {
+    fn default() -> Self {
+        Self {
+            group: Some(Duration::from_secs(24 * 60 * 60)),
+            session: Some(Duration::from_secs(60 * 60)),
+            finished_session: Some(Duration::from_secs(5 * 60)),
+            interval: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Heartbeat used to detect dead connections.
+///
+/// A duration of `None` disables the behaviour.
+#[derive(Debug, Clone)]
+pub struct Heartbeat {
+    /// Interval between websocket pings sent to each connection.
+    pub interval: Option<Duration>,
+    /// Duration a connection may be idle before it is closed.
+    ///
+    /// Any message received from a client including the reply
+    /// to a ping resets the idle time of the connection.
+    ///
+    /// When pings are enabled the timeout must be greater than
+    /// the interval.
+    pub timeout: Option<Duration>,
+}
+
+impl Default for Heartbeat {
+    fn default() -> Self {
+        Self {
+            interval: Some(Duration::from_secs(30)),
+            timeout: Some(Duration::from_secs(90)),
+        }
+    }
+}
+
+/// Verification of client certificates.
+#[derive(Debug, Clone)]
+pub enum ClientAuth {
+    /// Verify client certificates when they are presented.
+    ///
+    /// The path is the PEM encoded trust anchors.
+    Optional(PathBuf),
+    /// Require clients to present a valid certificate.
+    ///
+    /// The path is the PEM encoded trust anchors.
+    Required(PathBuf),
+}
+
+/// Options for TLS termination.
+#[derive(Debug, Clone)]
+pub struct TlsOptions {
+    /// Path to the PEM encoded certificate chain.
+    pub cert: PathBuf,
+    /// Path to the PEM encoded private key.
+    pub key: PathBuf,
+    /// Verification of client certificates.
+    pub client_auth: Option<ClientAuth>,
+}
+
+impl TlsOptions {
+    /// Create TLS options from certificate and key paths.
+    pub fn new(cert: PathBuf, key: PathBuf) -> Self {
+        Self {
+            cert,
+            key,
+            client_auth: None,
+        }
+    }
+
+    /// Set the verification of client certificates.
+    pub fn client_auth(mut self, client_auth: ClientAuth) -> Self {
+        self.client_auth = Some(client_auth);
+        self
+    }
+}
+
+/// Options used to configure a [Server](crate::Server).
+///
+/// The default options mount the websocket endpoint at `/mpc`,
+/// initialize the global tracing subscriber, do not serve static
+/// files and set the `Cross-Origin-Embedder-Policy` and
+/// `Cross-Origin-Opener-Policy` headers required for `SharedArrayBuffer`.
+#[derive(Debug, Clone)]
+pub struct ServerOptions {
+    pub(crate) path: String,
+    pub(crate) static_files: Option<PathBuf>,
+    pub(crate) headers: HeaderMap,
+    pub(crate) tracing: bool,
+    pub(crate) limits: Limits,
+    pub(crate) resume_grace_period: Duration,
+    pub(crate) tls: Option<TlsOptions>,
+    pub(crate) require_authentication: bool,
+    pub(crate) expiry: Expiry,
+    pub(crate) heartbeat: Heartbeat,
+    pub(crate) metrics: bool,
+    pub(crate) shutdown_timeout: Duration,
+    pub(crate) storage: Arc<dyn Storage>,
+    pub(crate) relay: Option<Arc<dyn RelayBackend>>,
+}
+
+impl Default for ServerOptions {
+    fn default() -> Self {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "Cross-Origin-Embedder-Policy",
+            HeaderValue::from_static("require-corp"),
+        );
+        headers.insert(
+            "Cross-Origin-Opener-Policy",
+            HeaderValue::from_static("same-origin"),
+        );
+
+        Self {
+            path: String::from("mpc"),
+            static_files: None,
+            headers,
+            tracing: true,
+            limits: Default::default(),
+            resume_grace_period: RESUME_GRACE_PERIOD,
+            tls: None,
+            require_authentication: false,
+            expiry: Default::default(),
+            heartbeat: Default::default(),
+            metrics: false,
+            shutdown_timeout: SHUTDOWN_TIMEOUT,
+            storage: Arc::new(MemoryStorage::default()),
+            relay: None,
+        }
+    }
+}
+
+impl ServerOptions {
+    /// Create server options with the websocket endpoint mounted at `path`.
+    pub fn new(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Serve static files from a directory.
+    pub fn static_files(mut self, static_files: PathBuf) -> Self {
+        self.static_files = Some(static_files);
+        self
+    }
+
+    /// Set whether the global tracing subscriber is initialized.
+    ///
+    /// Disable this when embedding the server in an application
+    /// that configures it's own tracing subscriber.
+    pub fn tracing(mut self, tracing: bool) -> Self {
+        self.tracing = tracing;
+        self
+    }
+
+    /// Replace the headers added to every response.
+    pub fn headers(mut self, headers: HeaderMap) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    /// Add a header to every response.
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+
+    /// Set the limits for websocket connections.
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Set the grace period that disconnected clients may
+    /// resume a connection.
+    ///
+    /// A zero duration disables resuming connections.
+    pub fn resume_grace_period(mut self, duration: Duration) -> Self {
+        self.resume_grace_period = duration;
+        self
+    }
+
+    /// Terminate TLS connections using the given options.
+    ///
+    /// Only applies when the server is run using
+    /// [Server::run](crate::Server::run).
+    pub fn tls(mut self, tls: TlsOptions) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    /// Set whether connections must be authenticated
+    /// to create or join groups.
+    ///
+    /// Groups created with an allowlist always require
+    /// authentication regardless of this option.
+    pub fn require_authentication(mut self, required: bool) -> Self {
+        self.require_authentication = required;
+        self
+    }
+
+    /// Set the time to live for groups and sessions.
+    pub fn expiry(mut self, expiry: Expiry) -> Self {
+        self.expiry = expiry;
+        self
+    }
+
+    /// Set the heartbeat used to detect dead connections.
+    pub fn heartbeat(mut self, heartbeat: Heartbeat) -> Self {
+        self.heartbeat = heartbeat;
+        self
+    }
+
+    /// Set whether metrics are exported in the Prometheus
+    /// text format from the `/metrics` route.
+    pub fn metrics(mut self, metrics: bool) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Set the time to wait for active sessions to be finished
+    /// when the server is shutting down.
+    pub fn shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.shutdown_timeout = timeout;
+        self
+    }
+
+    /// Set the storage for group and session metadata.
+    ///
+    /// Groups in the storage are loaded when the server is created.
+    pub fn storage(mut self, storage: Arc<dyn Storage>) -> Self {
+        self.storage = storage;
+        self
+    }
+
+    /// Share groups and sessions with other server instances
+    /// using a relay backend.
+    pub fn relay(mut self, relay: Arc<dyn RelayBackend>) -> Self {
+        self.relay = Some(relay);
+        self
+    }
+}
@@ -0,0 +1,608 @@
+//! Relay backends for running multiple server instances.
+//!
+//! Every instance keeps a replica of the routing information for
+//! all groups and sessions; when an instance changes a group it
+//! publishes a snapshot of the group which other instances apply
+//! to their replica. Messages for connections that belong to
+//! another instance are published to the relay and delivered by
+//! the instance that owns the connection.
+//!
+//! Each instance is authoritative for the group memberships and
+//! party signups of its own connections so concurrent changes on
+//! different instances are merged. Party signup numbers are assigned
+//! by the instance handling `Session.signup` so concurrent signups
+//! on different instances may be assigned the same number; when the
+//! changes are merged the signup from the lowest connection identifier
+//! is kept on every instance and the instance that owns the other
+//! connection emits a `sessionSignupRejected` event so that the
+//! client can sign up again.
+//!
+//! When a subscription is established all the groups are published
+//! so that instances which missed changes whilst the subscription
+//! was lost are brought up to date.
+//!
+//! Resume tokens and authenticated addresses are not shared so a
+//! client must resume a connection on the same instance.
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::{broadcast, mpsc, Mutex};
+use uuid::Uuid;
+use warp::ws::Message;
+
+use crate::auth::Address;
+use crate::services::{Event, SignupRejected};
+use crate::{
+    rpc_notify, Abort, Group, Notification, Parameters, Result, ServerError,
+    Session, SessionKind, State,
+};
+
+/// Backend used to exchange messages between server instances.
+#[async_trait]
+pub trait RelayBackend: fmt::Debug + Send + Sync {
+    /// Publish a payload to all the server instances.
+    async fn publish(&self, payload: Vec<u8>) -> Result<()>;
+
+    /// Subscribe to the payloads published by all the server instances.
+    ///
+    /// The receiver is closed when the subscription is lost.
+    async fn subscribe(&self) -> Result<mpsc::UnboundedReceiver<Vec<u8>>>;
+}
+
+/// Relay backend for server instances in the same process.
+#[derive(Debug)]
+pub struct LoopbackRelay {
+    tx: broadcast::Sender<Vec<u8>>,
+}
+
+impl LoopbackRelay {
+    /// Create a loopback relay buffering up to `capacity` payloads
+    /// for each subscriber.
+    pub fn new(capacity: usize) -> Self {
+        let (tx, _) = broadcast::channel(capacity);
+        Self { tx }
+    }
+}
+
+impl Default for LoopbackRelay {
+    fn default() -> Self {
+        Self::new(1024)
+    }
+}
+
+#[async_trait]
+impl RelayBackend for LoopbackRelay {
+    async fn publish(&self, payload: Vec<u8>) -> Result<()> {
+        // No subscribers is not an error
+        let _ = self.tx.send(payload);
+        Ok(())
+    }
+
+    async fn subscribe(&self) -> Result<mpsc::UnboundedReceiver<Vec<u8>>> {
+        let mut rx = self.tx.subscribe();
+        let (tx, subscription) = mpsc::unbounded_channel();
+        tokio::task::spawn(async move {
+            loop {
+                match rx.recv().await {
+                    Ok(payload) => {
+                        if tx.send(payload).is_err() {
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(count)) => {
+                        tracing::warn!(count, "relay subscriber lagged");
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+        Ok(subscription)
+    }
+}
+
+/// Relay backend using Redis publish and subscribe.
+///
+/// Only the `PUBLISH` and `SUBSCRIBE` commands are used so any
+/// server that speaks the Redis protocol may be used.
+#[derive(Debug)]
+pub struct RedisRelay {
+    addr: String,
+    channel: String,
+    connection: Mutex<Option<BufReader<TcpStream>>>,
+}
+
+impl RedisRelay {
+    /// Create a relay for the Redis server at `addr` (`host:port`)
+    /// publishing to `channel`.
+    pub fn new(addr: impl Into<String>, channel: impl Into<String>) -> Self {
+        Self {
+            addr: addr.into(),
+            channel: channel.into(),
+            connection: Mutex::new(None),
+        }
+    }
+
+    async fn connect(&self) -> Result<BufReader<TcpStream>> {
+        Ok(BufReader::new(TcpStream::connect(&self.addr).await?))
+    }
+}
+
+#[async_trait]
+impl RelayBackend for RedisRelay {
+    async fn publish(&self, payload: Vec<u8>) -> Result<()> {
+        let mut connection = self.connection.lock().await;
+        if connection.is_none() {
+            *connection = Some(self.connect().await?);
+        }
+        let stream = connection.as_mut().unwrap();
+        let command =
+            encode_command(&[b"PUBLISH", self.channel.as_bytes(), &payload]);
+        let result = async {
+            stream.get_mut().write_all(&command).await?;
+            match read_value(stream).await? {
+                Resp::Error(e) => Err(ServerError::Relay(e)),
+                _ => Ok(()),
+            }
+        }
+        .await;
+
+        // Reconnect on the next publish
+        if result.is_err() {
+            *connection = None;
+        }
+        result
+    }
+
+    async fn subscribe(&self) -> Result<mpsc::UnboundedReceiver<Vec<u8>>> {
+        let mut stream = self.connect().await?;
+        let command = encode_command(&[b"SUBSCRIBE", self.channel.as_bytes()]);
+        stream.get_mut().write_all(&command).await?;
+
+        let (tx, subscription) = mpsc::unbounded_channel();
+        tokio::task::spawn(async move {
+            loop {
+                match read_value(&mut stream).await {
+                    Ok(Resp::Array(mut values)) => {
+                        // Messages are [message, channel, payload]
+                        let is_message = matches!(
+                            values.first(),
+                            Some(Resp::Bulk(Some(kind))) if kind == b"message"
+                        );
+                        if is_message && values.len() == 3 {
+                            if let Resp::Bulk(Some(payload)) = values.remove(2)
+                            {
+                                if tx.send(payload).is_err() {
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        tracing::warn!(?e, "relay subscription closed");
+                        break;
+                    }
+                }
+            }
+        });
+        Ok(subscription)
+    }
+}
+
+/// Value in the Redis protocol.
+#[derive(Debug)]
+enum Resp {
+    Simple,
+    Error(String),
+    Integer,
+    Bulk(Option<Vec<u8>>),
+    Array(Vec<Resp>),
+}
+
+/// Encode a command as an array of bulk strings.
+fn encode_command(args: &[&[u8]]) -> Vec<u8> {
+    let mut out = format!("*{}\r\n", args.len()).into_bytes();
+    for arg in args {
+        out.extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+        out.extend_from_slice(arg);
+        out.extend_from_slice(b"\r\n");
+    }
+    out
+}
+
+/// Read a value in the Redis protocol.
+fn read_value<'a>(
+    stream: &'a mut BufReader<TcpStream>,
+) -> std::pin::Pin<
+    Box<dyn std::future::Future<Output = Result<Resp>> + Send + 'a>,
+> {
+    Box::pin(async move {
+        let mut line = String::new();
+        if stream.read_line(&mut line).await? == 0 {
+            return Err(ServerError::Relay("connection closed".to_string()));
+        }
+        let line = line.trim_end_matches("\r\n");
+        let (kind, rest) = line.split_at(line.len().min(1));
+        let parse_len = |value: &str| {
+            value.parse::<i64>().map_err(|_| {
+                ServerError::Relay(format!("bad length {}", value))
+            })
+        };
+        Ok(match kind {
+            "+" => Resp::Simple,
+            "-" => Resp::Error(rest.to_string()),
+            ":" => {
+                parse_len(rest)?;
+                Resp::Integer
+            }
+            "$" => {
+                let len = parse_len(rest)?;
+                if len < 0 {
+                    Resp::Bulk(None)
+                } else {
+                    let mut buf = vec![0u8; len as usize + 2];
+                    stream.read_exact(&mut buf).await?;
+                    buf.truncate(len as usize);
+                    Resp::Bulk(Some(buf))
+                }
+            }
+            "*" => {
+                let len = parse_len(rest)?;
+                let mut values = Vec::new();
+                for _ in 0..len.max(0) {
+                    values.push(read_value(stream).await?);
+                }
+                Resp::Array(values)
+            }
+            _ => {
+                return Err(ServerError::Relay(format!(
+                    "unexpected reply {}",
+                    line
+                )))
+            }
+        })
+    })
+}
+
+/// Prefix for the connection identifiers issued by a server instance.
+///
+/// Identifiers must be unique across server instances sharing a
+/// relay and remain safe integers for Javascript clients.
+pub(crate) fn connection_prefix(node_id: &Uuid) -> usize {
+    let bytes = node_id.as_bytes();
+    let prefix = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0]);
+    ((prefix as u64 & 0xfffff) << 32) as usize
+}
+
+/// Determine if a connection was issued by the server
+/// instance with `prefix`.
+pub(crate) fn is_owner(prefix: usize, conn: usize) -> bool {
+    (conn as u64) >> 32 == (prefix as u64) >> 32
+}
+
+/// Routing information for a session shared between instances.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SessionRoute {
+    uuid: Uuid,
+    kind: SessionKind,
+    value: Option<Value>,
+    age: Duration,
+    party_signups: Vec<(u16, usize)>,
+    finished: HashSet<u16>,
+    closed: bool,
+    aborted: Option<Abort>,
+}
+
+/// Routing information for a group shared between instances.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct GroupRoute {
+    uuid: Uuid,
+    params: Parameters,
+    label: String,
+    clients: Vec<usize>,
+    allowlist: Option<Vec<Address>>,
+    sessions: Vec<SessionRoute>,
+}
+
+impl From<&Group> for GroupRoute {
+    fn from(group: &Group) -> Self {
+        Self {
+            uuid: group.uuid,
+            params: group.params.clone(),
+            label: group.label.clone(),
+            clients: group.clients.clone(),
+            allowlist: group.allowlist.clone(),
+            sessions: group
+                .sessions
+                .values()
+                .map(|session| SessionRoute {
+                    uuid: session.uuid,
+                    kind: session.kind.clone(),
+                    value: session.value.clone(),
+                    age: session.created.elapsed(),
+                    party_signups: session.party_signups.clone(),
+                    finished: session.finished.clone(),
+                    closed: session.closed.is_some(),
+                    aborted: session.aborted.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl GroupRoute {
+    /// Create an empty replica of the group.
+    fn replica(&self) -> Group {
+        Group {
+            uuid: self.uuid,
+            params: self.params.clone(),
+            label: self.label.clone(),
+            allowlist: self.allowlist.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Merge the routing information published by the server
+    /// instance with connection `prefix` into a replica of the group.
+    ///
+    /// Each instance is authoritative for the group memberships,
+    /// party signups and finished parties of its own connections;
+    /// sessions unknown to the replica are added and a session
+    /// closed or aborted by any instance is closed or aborted.
+    ///
+    /// When a party number is signed up by more than one connection
+    /// the lowest connection identifier keeps the party number; the
+    /// rejected signups owned by the instance with `local_prefix` are
+    /// returned as `(conn, session, party_number)` tuples.
+    fn merge(
+        self,
+        prefix: usize,
+        local_prefix: usize,
+        group: &mut Group,
+    ) -> Vec<(usize, Uuid, u16)> {
+        let mut rejected = Vec::new();
+        group.clients.retain(|conn| !is_owner(prefix, *conn));
+        group.clients.extend(
+            self.clients
+                .into_iter()
+                .filter(|conn| is_owner(prefix, *conn)),
+        );
+        group.last_activity = Instant::now();
+
+        for route in self.sessions {
+            let params = &group.params;
+            let session =
+                group.sessions.entry(route.uuid).or_insert_with(|| {
+                    let mut session =
+                        Session::new(route.kind.clone(), route.value, params);
+                    session.uuid = route.uuid;
+                    session.created = Instant::now()
+                        .checked_sub(route.age)
+                        .unwrap_or_else(Instant::now);
+                    session
+                });
+
+            // Finished parties are owned by the connection that signed up
+            let finished = session
+                .party_signups
+                .iter()
+                .filter(|(number, conn)| {
+                    !is_owner(prefix, *conn)
+                        && session.finished.contains(number)
+                })
+                .map(|(number, _)| *number)
+                .chain(route.party_signups.iter().filter_map(
+                    |(number, conn)| {
+                        if is_owner(prefix, *conn)
+                            && route.finished.contains(number)
+                        {
+                            Some(*number)
+                        } else {
+                            None
+                        }
+                    },
+                ))
+                .collect::<HashSet<_>>();
+
+            session
+                .party_signups
+                .retain(|(_, conn)| !is_owner(prefix, *conn));
+            session.party_signups.extend(
+                route
+                    .party_signups
+                    .into_iter()
+                    .filter(|(_, conn)| is_owner(prefix, *conn)),
+            );
+            session.party_signups.sort();
+            session.party_signups.dedup_by(|duplicate, kept| {
+                if duplicate.0 == kept.0 {
+                    if is_owner(local_prefix, duplicate.1) {
+                        rejected.push((duplicate.1, session.uuid, duplicate.0));
+                    }
+                    true
+                } else {
+                    false
+                }
+            });
+            session.finished = finished;
+            session.full = session.party_signups.len() >= session.required;
+            if route.closed && session.closed.is_none() {
+                session.closed = Some(Instant::now());
+            }
+            if let (Some(abort), None) = (route.aborted, &session.aborted) {
+                session.abort(abort);
+            }
+        }
+        rejected
+    }
+}
+
+/// Message exchanged between server instances.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub(crate) enum Envelope {
+    /// Replace the replica of a group.
+    Group {
+        /// Routing information for the group.
+        group: GroupRoute,
+    },
+    /// Remove the replica of a group.
+    RemoveGroup {
+        /// Group identifier.
+        group_id: Uuid,
+    },
+    /// Deliver an encoded message to a connection.
+    Deliver {
+        /// Connection identifier.
+        conn_id: usize,
+        /// Encoded message.
+        message: String,
+    },
+}
+
+/// Envelope with the identifier of the instance that published it.
+#[derive(Debug, Serialize, Deserialize)]
+struct Packet {
+    origin: Uuid,
+    envelope: Envelope,
+}
+
+/// Publish an envelope to the other server instances.
+pub(crate) async fn publish(
+    backend: &Arc<dyn RelayBackend>,
+    origin: Uuid,
+    envelope: Envelope,
+) {
+    let packet = Packet { origin, envelope };
+    let payload = serde_json::to_vec(&packet).unwrap();
+    if let Err(e) = backend.publish(payload).await {
+        tracing::error!(?e, "failed to publish to relay");
+    }
+}
+
+/// Apply a payload published by another server instance.
+pub(crate) async fn receive(state: &Arc<State>, payload: &[u8]) {
+    let packet: Packet = match serde_json::from_slice(payload) {
+        Ok(packet) => packet,
+        Err(e) => {
+            tracing::warn!(?e, "invalid relay payload");
+            return;
+        }
+    };
+
+    if packet.origin == state.node_id {
+        return;
+    }
+    let prefix = connection_prefix(&packet.origin);
+
+    match packet.envelope {
+        Envelope::Group { group: route } => {
+            let group_id = route.uuid;
+            let rejected = if let Some(group) = state.groups.get(&group_id) {
+                route.merge(prefix, state.conn_prefix, &mut *group.lock().await)
+            } else {
+                let mut group = route.replica();
+                let rejected =
+                    route.merge(prefix, state.conn_prefix, &mut group);
+                state.groups.insert(group);
+                rejected
+            };
+
+            if !rejected.is_empty() {
+                // Other instances must drop the rejected signups
+                state.changes.changed(group_id);
+                publish_changes(state).await;
+
+                let messages = rejected
+                    .into_iter()
+                    .map(|(conn_id, session_id, party_number)| {
+                        tracing::warn!(
+                            conn_id,
+                            %session_id,
+                            party_number,
+                            "session signup rejected"
+                        );
+                        let rejected = SignupRejected {
+                            session_id,
+                            party_number,
+                        };
+                        (conn_id, Event::SessionSignupRejected(rejected).into())
+                    })
+                    .collect();
+                rpc_notify(state, Notification::Relay { messages }).await;
+            }
+        }
+        Envelope::RemoveGroup { group_id } => {
+            if let Some(group) = state.groups.get(&group_id) {
+                let mut group = group.lock().await;
+                for conn in &group.clients {
+                    state.leave_group(*conn);
+                }
+                state.groups.remove(&mut group);
+            }
+        }
+        Envelope::Deliver { conn_id, message } => {
+            if let Some(client) = state.clients.get(&conn_id) {
+                client.send(conn_id, Message::text(message), state);
+            }
+        }
+    }
+}
+
+/// Changes to groups that need to be published.
+#[derive(Debug, Default)]
+pub(crate) struct Changes {
+    changed: StdMutex<HashSet<Uuid>>,
+    removed: StdMutex<HashSet<Uuid>>,
+}
+
+impl Changes {
+    /// Mark a group as changed.
+    pub(crate) fn changed(&self, group_id: Uuid) {
+        self.changed.lock().unwrap().insert(group_id);
+    }
+
+    /// Mark a group as removed.
+    pub(crate) fn removed(&self, group_id: Uuid) {
+        self.changed.lock().unwrap().remove(&group_id);
+        self.removed.lock().unwrap().insert(group_id);
+    }
+
+    fn take(&self) -> (HashSet<Uuid>, HashSet<Uuid>) {
+        (
+            std::mem::take(&mut *self.changed.lock().unwrap()),
+            std::mem::take(&mut *self.removed.lock().unwrap()),
+        )
+    }
+}
+
+/// Publish the changes to groups to the other server instances.
+pub(crate) async fn publish_changes(state: &Arc<State>) {
+    let (changed, removed) = state.changes.take();
+    let backend = match &state.options.relay {
+        Some(backend) => backend,
+        None => return,
+    };
+
+    for group_id in changed {
+        let route = match state.groups.get(&group_id) {
+            Some(group) => GroupRoute::from(&*group.lock().await),
+            None => continue,
+        };
+        let envelope = Envelope::Group { group: route };
+        publish(backend, state.node_id, envelope).await;
+    }
+    for group_id in removed {
+        let envelope = Envelope::RemoveGroup { group_id };
+        publish(backend, state.node_id, envelope).await;
+    }
+}
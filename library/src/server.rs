@@ -1,30 +1,66 @@
 use std::collections::{HashMap, HashSet};
-use std::net::SocketAddr;
+use std::fmt;
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
 use std::path::PathBuf;
 use std::sync::{
-    atomic::{AtomicUsize, Ordering},
-    Arc,
+    atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+    Arc, Mutex as StdMutex, RwLock as StdRwLock,
 };
+use std::time::{Duration, Instant};
 
+use arc_swap::ArcSwap;
 use futures_util::{SinkExt, StreamExt, TryFutureExt};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use thiserror::Error;
-use tokio::sync::{mpsc, Mutex, RwLock};
-use tokio_stream::wrappers::UnboundedReceiverStream;
+use tokio::sync::{mpsc, watch, Mutex, RwLock};
 use uuid::Uuid;
-use warp::http::header::{HeaderMap, HeaderValue};
+use warp::filters::BoxedFilter;
+use warp::http::StatusCode;
 use warp::ws::{Message, WebSocket};
-use warp::Filter;
+use warp::{Filter, Reply};
 
+use crate::auth::{self, Address};
+use crate::relay::{self, Changes};
 use crate::services::*;
-use json_rpc2::{Request, Response};
+use crate::storage::GroupRecord;
+use crate::{ClientAuth, Expiry, Metrics, ServerOptions};
+use json_rpc2::{Request, Response, RpcError};
 
 use tracing_subscriber::fmt::format::FmtSpan;
 
 /// Global unique connection id counter.
 static CONNECTION_ID: AtomicUsize = AtomicUsize::new(1);
 
+/// Default grace period that a disconnected client may
+/// resume the connection before it is removed from groups
+/// and sessions.
+pub const RESUME_GRACE_PERIOD: Duration = Duration::from_secs(30);
+
+/// Default maximum number of outgoing messages queued for a connection.
+pub const SEND_QUEUE_CAPACITY: usize = 1024;
+
+/// Default time to wait for active sessions to be finished
+/// when the server is shutting down.
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Time allowed to send final messages to a client before
+/// the websocket is closed.
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Interval between checks for active sessions and connections
+/// when the server is shutting down.
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Interval between attempts to subscribe to the relay backend.
+const RELAY_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Origin named in authentication challenges when the
+/// request does not include a `Host` header.
+const DEFAULT_ORIGIN: &str = "this server";
+
 /// Error thrown by the server.
 #[derive(Debug, Error)]
 pub enum ServerError {
@@ -32,6 +68,10 @@ pub enum ServerError {
     #[error("{0} is not a directory")]
     NotDirectory(PathBuf),
 
+    /// Error generated when a file is expected.
+    #[error("{0} is not a file")]
+    NotFile(PathBuf),
+
     /// Error generated if party number is zero.
     #[error("party number may not be zero")]
     ZeroPartyNumber,
@@ -44,6 +84,74 @@ pub enum ServerError {
     #[error("party number already exists for session {0}")]
     PartyNumberAlreadyExists(Uuid),
 
+    /// Error generated when a resume token is not valid or has expired.
+    #[error("resume token is not valid")]
+    BadResumeToken,
+
+    /// Error generated when attempting to resume a connection
+    /// that is still connected.
+    #[error("connection for the resume token is still active")]
+    ConnectionActive,
+
+    /// Error generated when attempting to resume on a connection
+    /// that already has a resume token, group memberships or
+    /// party signups of its own.
+    #[error("connection {0} is already in use and cannot be resumed")]
+    ConnectionInUse(usize),
+
+    /// Error generated when the required number of parties
+    /// have already signed up to a session.
+    #[error("session {0} is full")]
+    SessionFull(Uuid),
+
+    /// Error generated when the send queue capacity is zero.
+    #[error("send queue capacity must be greater than zero")]
+    ZeroSendQueueCapacity,
+
+    /// Error generated when the heartbeat interval is zero.
+    #[error("heartbeat interval must be greater than zero")]
+    ZeroHeartbeatInterval,
+
+    /// Error generated when the idle timeout is zero.
+    #[error("idle timeout must be greater than zero")]
+    ZeroIdleTimeout,
+
+    /// Error generated when the idle timeout does not exceed the
+    /// heartbeat interval.
+    #[error("idle timeout must be greater than the heartbeat interval")]
+    IdleTimeoutTooShort,
+
+    /// Error generated when the expiry interval is zero.
+    #[error("expiry interval must be greater than zero")]
+    ZeroExpiryInterval,
+
+    /// Error sent to a client that is disconnected because
+    /// the queue of outgoing messages is full.
+    #[error("send queue for connection {0} is full")]
+    SendQueueFull(usize),
+
+    /// Error sent to a client when a request exceeds the maximum size.
+    #[error("request exceeds the maximum size of {0} bytes")]
+    RequestTooLarge(usize),
+
+    /// Error sent to a client that sends too many requests.
+    #[error("connection {0} exceeded the request rate limit")]
+    RateLimited(usize),
+
+    /// Error sent to a client that is disconnected because there
+    /// are too many connections from the IP address.
+    #[error("too many connections from {0}")]
+    TooManyConnections(IpAddr),
+
+    /// Error sent to a client that is disconnected because the
+    /// requested protocol version is not supported.
+    #[error("protocol version {0} is not supported, expected {1}")]
+    ProtocolVersion(u32, u32),
+
+    /// Error generated by a relay backend.
+    #[error("relay error: {0}")]
+    Relay(String),
+
     /// Error generated parsing a socket address.
     #[error(transparent)]
     NetAddrParse(#[from] std::net::AddrParseError),
@@ -55,6 +163,10 @@ pub enum ServerError {
     /// Error generated by the JSON-RPC services.
     #[error(transparent)]
     JsonRpcError(#[from] json_rpc2::Error),
+
+    /// Error generated encoding or decoding JSON.
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
 }
 
 /// Result type for server errors.
@@ -74,32 +186,51 @@ pub struct Parameters {
 
 impl Default for Parameters {
     fn default() -> Self {
-        return Self {
+        Self {
             parties: 3,
             threshold: 1,
-        };
+        }
     }
 }
 
 /// Represents the type of session.
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(
+    Debug, Default, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq,
+)]
 pub enum SessionKind {
     /// Key generation session.
     #[serde(rename = "keygen")]
+    #[default]
     Keygen,
     /// Signing session.
     #[serde(rename = "sign")]
     Sign,
 }
 
-impl Default for SessionKind {
-    fn default() -> Self {
-        SessionKind::Keygen
+impl fmt::Display for SessionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionKind::Keygen => write!(f, "keygen"),
+            SessionKind::Sign => write!(f, "sign"),
+        }
+    }
+}
+
+impl SessionKind {
+    /// Number of parties required for a session of this kind.
+    ///
+    /// Key generation requires `parties` and signing
+    /// requires `threshold + 1` parties.
+    pub fn required_parties(&self, params: &Parameters) -> u16 {
+        match self {
+            SessionKind::Keygen => params.parties,
+            SessionKind::Sign => params.threshold + 1,
+        }
     }
 }
 
 /// Group is a collection of connected websocket clients.
-#[derive(Debug, Default, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Group {
     /// Unique identifier for the group.
     pub uuid: Uuid,
@@ -110,9 +241,37 @@ pub struct Group {
     /// Collection of client identifiers.
     #[serde(skip)]
     pub(crate) clients: Vec<usize>,
+    /// Opaque member identifiers for the clients.
+    #[serde(skip)]
+    pub(crate) members: HashMap<usize, Uuid>,
     /// Sessions belonging to this group.
     #[serde(skip)]
     pub(crate) sessions: HashMap<Uuid, Session>,
+    /// Addresses of authenticated connections allowed to join the group.
+    #[serde(skip)]
+    pub(crate) allowlist: Option<Vec<Address>>,
+    /// Last time the group was accessed by a client.
+    #[serde(skip)]
+    pub(crate) last_activity: Instant,
+    /// Whether the group has been removed from the server.
+    #[serde(skip)]
+    pub(crate) removed: bool,
+}
+
+impl Default for Group {
+    fn default() -> Self {
+        Self {
+            uuid: Default::default(),
+            params: Default::default(),
+            label: Default::default(),
+            clients: Default::default(),
+            members: Default::default(),
+            sessions: Default::default(),
+            allowlist: None,
+            last_activity: Instant::now(),
+            removed: false,
+        }
+    }
 }
 
 impl Group {
@@ -123,15 +282,79 @@ impl Group {
         Self {
             uuid: Uuid::new_v4(),
             clients: vec![conn],
+            members: HashMap::from([(conn, Uuid::new_v4())]),
             sessions: Default::default(),
+            allowlist: None,
+            last_activity: Instant::now(),
+            removed: false,
             params,
             label,
         }
     }
+
+    /// Determine if a connection authenticated as `address`
+    /// is authorized to access this group.
+    ///
+    /// When the group does not have an allowlist all
+    /// connections are authorized.
+    pub fn is_authorized(&self, address: Option<&Address>) -> bool {
+        if let Some(allowlist) = &self.allowlist {
+            address.map(|a| allowlist.contains(a)).unwrap_or(false)
+        } else {
+            true
+        }
+    }
+
+    /// Add a connection to the group.
+    ///
+    /// Returns the member identifier issued to the connection.
+    pub fn add_connection(&mut self, conn: usize) -> Uuid {
+        self.clients.push(conn);
+        *self.members.entry(conn).or_insert_with(Uuid::new_v4)
+    }
+
+    /// Remove a connection from the group and the party
+    /// signups for the connection from all the sessions.
+    ///
+    /// Returns the member identifier when the connection was a
+    /// member of the group and the session identifiers and party
+    /// numbers for the party signups that were removed.
+    pub fn remove_connection(
+        &mut self,
+        conn: usize,
+    ) -> (Option<Uuid>, Vec<(Uuid, u16)>) {
+        let member =
+            if let Some(index) = self.clients.iter().position(|c| *c == conn) {
+                self.clients.remove(index);
+                Some(self.members.remove(&conn).unwrap_or_default())
+            } else {
+                None
+            };
+
+        let mut departed = Vec::new();
+        for (session_id, session) in self.sessions.iter_mut() {
+            for party_number in session.remove_connection(conn) {
+                departed.push((*session_id, party_number));
+            }
+        }
+        (member, departed)
+    }
+}
+
+/// Reason given by a participant for aborting a session.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Abort {
+    /// Party number of the participant that aborted the session.
+    pub party_number: u16,
+    /// Code for the reason the session was aborted.
+    pub code: String,
+    /// Message describing the reason the session was aborted.
+    pub message: String,
 }
 
 /// Session used for key generation or signing communication.
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Session {
     /// Unique identifier for the session.
     pub uuid: Uuid,
@@ -148,6 +371,14 @@ pub struct Session {
     /// a signing session.
     pub value: Option<Value>,
 
+    /// Whether the required number of parties
+    /// have signed up to the session.
+    pub full: bool,
+
+    /// Number of parties required for the session.
+    #[serde(skip)]
+    pub(crate) required: usize,
+
     /// Map party number to connection identifier
     #[serde(skip)]
     pub(crate) party_signups: Vec<(u16, usize)>,
@@ -156,47 +387,165 @@ pub struct Session {
     /// marked the session as finished.
     #[serde(skip)]
     pub(crate) finished: HashSet<u16>,
+
+    /// Time the session was created.
+    #[serde(skip, default = "Instant::now")]
+    pub(crate) created: Instant,
+
+    /// Time all the participants finished the session
+    /// or the session was aborted.
+    #[serde(skip)]
+    pub(crate) closed: Option<Instant>,
+
+    /// Reason the session was aborted.
+    #[serde(skip)]
+    pub(crate) aborted: Option<Abort>,
 }
 
 impl Default for Session {
     fn default() -> Self {
-        Self {
-            uuid: Uuid::new_v4(),
-            kind: Default::default(),
-            party_signups: Default::default(),
-            finished: Default::default(),
-            value: None,
-        }
+        Self::new(Default::default(), None, &Default::default())
     }
 }
 
-impl From<(SessionKind, Option<Value>)> for Session {
-    fn from(value: (SessionKind, Option<Value>)) -> Session {
+impl Session {
+    /// Create a new session.
+    ///
+    /// The group `params` determine the number of
+    /// parties required for the session `kind`.
+    pub fn new(
+        kind: SessionKind,
+        value: Option<Value>,
+        params: &Parameters,
+    ) -> Self {
         Self {
             uuid: Uuid::new_v4(),
-            kind: value.0,
+            required: kind.required_parties(params) as usize,
+            kind,
             party_signups: Default::default(),
             finished: Default::default(),
-            value: value.1,
+            value,
+            full: false,
+            created: Instant::now(),
+            closed: None,
+            aborted: None,
+        }
+    }
+
+    /// Update the full state of the session.
+    fn update_full(&mut self) {
+        self.full = self.party_signups.len() >= self.required;
+    }
+
+    /// Determine if this session has expired.
+    fn is_expired(&self, expiry: &Expiry) -> bool {
+        if let Some(closed) = self.closed {
+            expiry
+                .finished_session
+                .map(|ttl| closed.elapsed() > ttl)
+                .unwrap_or(false)
+        } else {
+            expiry
+                .session
+                .map(|ttl| self.created.elapsed() > ttl)
+                .unwrap_or(false)
         }
     }
-}
 
-impl Session {
     /// Signup to a session.
     ///
     /// This marks a connected client as actively participating in
     /// this session and issues them a unique party signup number.
-    pub fn signup(&mut self, conn: usize) -> u16 {
-        let last = self.party_signups.last();
-        let num = if last.is_none() {
-            1
-        } else {
-            let (num, _) = last.unwrap();
-            num + 1
-        };
+    ///
+    /// If the connection has already signed up the existing
+    /// party number is returned.
+    ///
+    /// The lowest available party number is issued so that slots
+    /// freed when a client disconnects are re-used.
+    pub fn signup(&mut self, conn: usize) -> Result<u16> {
+        if let Some(num) = self.party_number(conn) {
+            return Ok(num);
+        }
+
+        if self.full {
+            return Err(ServerError::SessionFull(self.uuid));
+        }
+
+        let mut num = 1;
+        while self.party_signups.iter().any(|(n, _)| *n == num) {
+            num += 1;
+        }
         self.party_signups.push((num, conn));
-        num
+        self.update_full();
+        Ok(num)
+    }
+
+    /// Party numbers signed up to this session in ascending order.
+    pub fn participants(&self) -> Vec<u16> {
+        let mut participants = self
+            .party_signups
+            .iter()
+            .map(|(n, _)| *n)
+            .collect::<Vec<u16>>();
+        participants.sort_unstable();
+        participants
+    }
+
+    /// Close the session when every remaining party signup
+    /// has marked the session as finished.
+    ///
+    /// Returns the party numbers in ascending order when
+    /// the session is closed.
+    pub fn close_if_finished(&mut self) -> Option<Vec<u16>> {
+        if self.closed.is_some() || self.finished.is_empty() {
+            return None;
+        }
+        let mut completed = self.finished.iter().cloned().collect::<Vec<u16>>();
+        completed.sort_unstable();
+        if self.participants() == completed {
+            self.closed = Some(Instant::now());
+            Some(completed)
+        } else {
+            None
+        }
+    }
+
+    /// Party number for a connection signed up to this session.
+    pub fn party_number(&self, conn: usize) -> Option<u16> {
+        self.party_signups
+            .iter()
+            .find(|(_, c)| *c == conn)
+            .map(|(n, _)| *n)
+    }
+
+    /// Abort this session.
+    ///
+    /// An aborted session is closed so that it expires in the
+    /// same way as a finished session.
+    pub fn abort(&mut self, abort: Abort) {
+        self.closed.get_or_insert_with(Instant::now);
+        self.aborted = Some(abort);
+    }
+
+    /// Remove all the party signups for a connection.
+    ///
+    /// Party numbers removed from the signups are also removed
+    /// from the set of finished parties.
+    ///
+    /// Returns the party numbers that were removed.
+    pub fn remove_connection(&mut self, conn: usize) -> Vec<u16> {
+        let removed = self
+            .party_signups
+            .iter()
+            .filter(|(_, c)| *c == conn)
+            .map(|(n, _)| *n)
+            .collect::<Vec<u16>>();
+        self.party_signups.retain(|(_, c)| *c != conn);
+        for party_number in &removed {
+            self.finished.remove(party_number);
+        }
+        self.update_full();
+        removed
     }
 
     /// Load an existing party signup number into this session.
@@ -215,31 +564,481 @@ impl Session {
         if party_number > parameters.parties {
             return Err(ServerError::PartyNumberOutOfRange);
         }
-        if let Some(_) = self
+        if self.full {
+            return Err(ServerError::SessionFull(self.uuid));
+        }
+        if self
             .party_signups
             .iter()
-            .find(|(num, _)| num == &party_number)
+            .any(|(num, _)| *num == party_number)
         {
-            return Err(ServerError::PartyNumberAlreadyExists(
-                self.uuid.clone(),
-            ));
+            return Err(ServerError::PartyNumberAlreadyExists(self.uuid));
         }
         self.party_signups.push((party_number, conn));
+        self.update_full();
         Ok(())
     }
 }
 
-/// Collection of clients and groups managed by the server.
+/// Sender for the outgoing messages of a connected client.
 #[derive(Debug)]
+pub struct ClientSender {
+    tx: mpsc::Sender<Message>,
+    capacity: usize,
+    close: watch::Sender<Close>,
+}
+
+/// Reason the server closes a connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Close {
+    /// Connection is open.
+    Open,
+    /// Client was too slow consuming messages.
+    Evicted,
+    /// Server is shutting down.
+    Shutdown,
+}
+
+impl ClientSender {
+    /// Number of messages waiting to be sent to the client.
+    pub fn queue_depth(&self) -> usize {
+        self.capacity - self.tx.capacity()
+    }
+
+    /// Queue a message for the client.
+    ///
+    /// The client is evicted when the queue is full.
+    pub(crate) fn send(&self, conn_id: usize, message: Message, state: &State) {
+        match self.tx.try_send(message) {
+            Ok(_) => {}
+            // The client is too slow consuming messages so evict it
+            Err(mpsc::error::TrySendError::Full(_)) => {
+                if *self.close.borrow() == Close::Open {
+                    tracing::warn!(conn_id, "send queue full, evicting");
+                    state.evicted.fetch_add(1, Ordering::Relaxed);
+                    let _ = self.close.send(Close::Evicted);
+                }
+            }
+            // The tx is disconnected, our `client_disconnected` code
+            // should be happening in another task, nothing more to
+            // do here.
+            Err(mpsc::error::TrySendError::Closed(_)) => {}
+        }
+    }
+}
+
+/// Depth of the outgoing message queues for connected clients.
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueMetrics {
+    /// Number of connected clients.
+    pub clients: usize,
+    /// Total number of queued messages for all clients.
+    pub queued: usize,
+    /// Largest number of queued messages for a single client.
+    pub max_depth: usize,
+    /// Number of clients evicted because the queue was full.
+    pub evicted: u64,
+}
+
+/// Health of the server returned by the health and readiness routes.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Health {
+    /// Whether the server is accepting new groups and sessions.
+    pub ready: bool,
+    /// Number of seconds since the server was created.
+    pub uptime: u64,
+    /// Version of the JSON-RPC protocol.
+    pub protocol_version: u32,
+    /// Number of connected clients.
+    pub clients: usize,
+    /// Number of groups.
+    pub groups: usize,
+    /// Number of sessions.
+    pub sessions: usize,
+}
+
+/// Senders for connected clients.
+///
+/// Lookups are lock-free so that relaying messages never waits
+/// for a lock; the map is replaced when a client connects or
+/// disconnects.
+#[derive(Debug, Default)]
+pub struct Clients {
+    senders: ArcSwap<HashMap<usize, Arc<ClientSender>>>,
+}
+
+impl Clients {
+    /// Number of connected clients.
+    pub fn len(&self) -> usize {
+        self.senders.load().len()
+    }
+
+    /// Determine if there are no connected clients.
+    pub fn is_empty(&self) -> bool {
+        self.senders.load().is_empty()
+    }
+
+    /// Determine if a client is connected.
+    pub fn contains(&self, conn_id: &usize) -> bool {
+        self.senders.load().contains_key(conn_id)
+    }
+
+    /// Identifiers of the connected clients.
+    pub fn ids(&self) -> Vec<usize> {
+        self.senders.load().keys().copied().collect()
+    }
+
+    /// Get the sender for a client.
+    pub(crate) fn get(&self, conn_id: &usize) -> Option<Arc<ClientSender>> {
+        self.senders.load().get(conn_id).cloned()
+    }
+
+    /// Get the senders for all connected clients.
+    pub(crate) fn all(&self) -> Arc<HashMap<usize, Arc<ClientSender>>> {
+        self.senders.load_full()
+    }
+
+    fn insert(&self, conn_id: usize, sender: ClientSender) {
+        let sender = Arc::new(sender);
+        self.senders.rcu(|senders| {
+            let mut senders = HashMap::clone(senders);
+            senders.insert(conn_id, Arc::clone(&sender));
+            senders
+        });
+    }
+
+    fn remove(&self, conn_id: &usize) {
+        self.senders.rcu(|senders| {
+            let mut senders = HashMap::clone(senders);
+            senders.remove(conn_id);
+            senders
+        });
+    }
+}
+
+/// Groups managed by the server.
+///
+/// Each group has it's own lock so that requests for
+/// different groups do not contend with each other; the
+/// lock for the collection is only held to look up, insert
+/// or remove a group and never whilst waiting.
+#[derive(Debug, Default)]
+pub struct Groups {
+    groups: StdRwLock<HashMap<Uuid, Arc<Mutex<Group>>>>,
+}
+
+impl Groups {
+    /// Number of groups.
+    pub fn len(&self) -> usize {
+        self.groups.read().unwrap().len()
+    }
+
+    /// Determine if there are no groups.
+    pub fn is_empty(&self) -> bool {
+        self.groups.read().unwrap().is_empty()
+    }
+
+    /// Determine if a group exists.
+    pub fn contains(&self, group_id: &Uuid) -> bool {
+        self.groups.read().unwrap().contains_key(group_id)
+    }
+
+    /// Get a group.
+    pub fn get(&self, group_id: &Uuid) -> Option<Arc<Mutex<Group>>> {
+        self.groups.read().unwrap().get(group_id).cloned()
+    }
+
+    /// Get all the groups.
+    pub fn all(&self) -> Vec<Arc<Mutex<Group>>> {
+        self.groups.read().unwrap().values().cloned().collect()
+    }
+
+    /// Insert a group.
+    pub(crate) fn insert(&self, group: Group) {
+        self.groups
+            .write()
+            .unwrap()
+            .insert(group.uuid, Arc::new(Mutex::new(group)));
+    }
+
+    /// Remove a group whilst holding the lock for the group.
+    ///
+    /// Requests waiting for the lock observe that the group
+    /// has been removed.
+    pub(crate) fn remove(&self, group: &mut Group) {
+        group.removed = true;
+        self.groups.write().unwrap().remove(&group.uuid);
+    }
+}
+
+/// Collection of clients and groups managed by the server.
+#[derive(Debug, Default)]
 pub struct State {
     /// Connected clients.
-    pub clients: HashMap<usize, mpsc::UnboundedSender<Message>>,
+    pub clients: Clients,
     /// Groups keyed by unique identifier (UUID)
-    pub groups: HashMap<Uuid, Group>,
+    pub groups: Groups,
+    /// Resume tokens keyed by connection identifier.
+    pub(crate) tokens: StdMutex<HashMap<usize, Uuid>>,
+    /// Pending authentication challenges keyed by connection identifier.
+    pub(crate) challenges: StdMutex<HashMap<usize, String>>,
+    /// Authenticated addresses keyed by connection identifier.
+    pub(crate) addresses: StdMutex<HashMap<usize, Address>>,
+    /// Options for the server.
+    pub(crate) options: ServerOptions,
+    /// Number of clients evicted because the send queue was full.
+    pub(crate) evicted: AtomicU64,
+    /// Metrics collected by the server.
+    pub(crate) metrics: Arc<Metrics>,
+    /// Whether the server is draining connections before shutdown.
+    pub(crate) draining: AtomicBool,
+    /// Unique identifier for this server instance.
+    pub(crate) node_id: Uuid,
+    /// Prefix for connection identifiers issued by this server instance.
+    pub(crate) conn_prefix: usize,
+    /// Changes to groups to publish to other server instances.
+    pub(crate) changes: Changes,
+    /// Number of connections keyed by IP address.
+    pub(crate) ip_connections: StdMutex<HashMap<IpAddr, usize>>,
+    /// Number of groups joined keyed by connection identifier.
+    pub(crate) group_counts: StdMutex<HashMap<usize, usize>>,
+}
+
+impl State {
+    /// Save the metadata for a group to storage.
+    pub(crate) fn save_group(&self, group: &Group) -> Result<()> {
+        if !group.removed {
+            self.options.storage.save(GroupRecord::from(group))?;
+        }
+        Ok(())
+    }
+
+    /// Register a connection from an IP address.
+    ///
+    /// Returns `false` if the address already has the maximum
+    /// number of connections.
+    fn connect_ip(&self, ip: IpAddr) -> bool {
+        let mut connections = self.ip_connections.lock().unwrap();
+        let count = connections.entry(ip).or_insert(0);
+        match self.options.limits.max_connections_per_ip {
+            Some(max) if *count >= max => false,
+            _ => {
+                *count += 1;
+                true
+            }
+        }
+    }
+
+    /// Remove a connection from an IP address.
+    fn disconnect_ip(&self, ip: IpAddr) {
+        let mut connections = self.ip_connections.lock().unwrap();
+        if let Some(count) = connections.get_mut(&ip) {
+            *count -= 1;
+            if *count == 0 {
+                connections.remove(&ip);
+            }
+        }
+    }
+
+    /// Register a connection joining a group.
+    ///
+    /// Returns `false` if the connection is already a member
+    /// of the maximum number of groups.
+    pub(crate) fn join_group(&self, conn: usize) -> bool {
+        let mut counts = self.group_counts.lock().unwrap();
+        let count = counts.entry(conn).or_insert(0);
+        match self.options.limits.max_groups_per_connection {
+            Some(max) if *count >= max => false,
+            _ => {
+                *count += 1;
+                true
+            }
+        }
+    }
+
+    /// Remove a connection from a group.
+    pub(crate) fn leave_group(&self, conn: usize) {
+        let mut counts = self.group_counts.lock().unwrap();
+        if let Some(count) = counts.get_mut(&conn) {
+            *count -= 1;
+            if *count == 0 {
+                counts.remove(&conn);
+            }
+        }
+    }
+
+    /// Remove a group from memory and storage whilst holding
+    /// the lock for the group.
+    ///
+    /// Groups are kept in storage when the server is shutting down.
+    pub(crate) fn remove_group(&self, group: &mut Group) {
+        let group_id = group.uuid;
+        for conn in &group.clients {
+            self.leave_group(*conn);
+        }
+        self.groups.remove(group);
+        self.changes.removed(group_id);
+
+        // Keep groups in storage when shutting down
+        if self.is_draining() {
+            return;
+        }
+        if let Err(e) = self.options.storage.remove(&group_id) {
+            tracing::error!(%group_id, ?e, "failed to remove group");
+        }
+    }
+
+    /// Whether the server is draining connections before shutdown.
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Relaxed)
+    }
+
+    /// Address for an authenticated connection.
+    pub(crate) fn address(&self, conn_id: &usize) -> Option<Address> {
+        self.addresses.lock().unwrap().get(conn_id).copied()
+    }
+
+    /// Summarize the health of the server.
+    ///
+    /// The server is not ready when it is draining
+    /// connections before shutdown.
+    pub async fn health(&self, uptime: Duration) -> Health {
+        let mut sessions = 0;
+        for group in self.groups.all() {
+            sessions += group.lock().await.sessions.len();
+        }
+        Health {
+            ready: !self.is_draining(),
+            uptime: uptime.as_secs(),
+            protocol_version: PROTOCOL_VERSION,
+            clients: self.clients.len(),
+            groups: self.groups.len(),
+            sessions,
+        }
+    }
+
+    /// Get the depth of the outgoing message queues.
+    pub fn queue_metrics(&self) -> QueueMetrics {
+        let clients = self.clients.all();
+        let mut metrics = QueueMetrics {
+            clients: clients.len(),
+            evicted: self.evicted.load(Ordering::Relaxed),
+            ..Default::default()
+        };
+        for client in clients.values() {
+            let depth = client.queue_depth();
+            metrics.queued += depth;
+            metrics.max_depth = metrics.max_depth.max(depth);
+        }
+        metrics
+    }
+
+    /// Get the resume token for a connection.
+    ///
+    /// A new token is issued if the connection does not have a token yet.
+    pub fn resume_token(&self, conn: usize) -> Uuid {
+        *self
+            .tokens
+            .lock()
+            .unwrap()
+            .entry(conn)
+            .or_insert_with(Uuid::new_v4)
+    }
+
+    /// Resume a connection.
+    ///
+    /// The group memberships and party signups belonging to the
+    /// disconnected client that owns `token` are rebound to `conn`
+    /// and the token is transferred to `conn`; if the disconnected
+    /// client was authenticated the address is also transferred.
+    ///
+    /// A connection that already owns a different resume token or
+    /// belongs to any group cannot be resumed.
+    ///
+    /// Returns the identifiers of the groups the connection belongs to.
+    pub async fn resume(&self, conn: usize, token: &Uuid) -> Result<Vec<Uuid>> {
+        let owned = self.tokens.lock().unwrap().get(&conn) == Some(token);
+        if !owned {
+            for group in self.groups.all() {
+                let group = group.lock().await;
+                let signed_up = group.sessions.values().any(|session| {
+                    session.party_signups.iter().any(|(_, c)| *c == conn)
+                });
+                if group.clients.contains(&conn) || signed_up {
+                    return Err(ServerError::ConnectionInUse(conn));
+                }
+            }
+        }
+
+        let previous = {
+            let mut tokens = self.tokens.lock().unwrap();
+            if !owned && tokens.contains_key(&conn) {
+                return Err(ServerError::ConnectionInUse(conn));
+            }
+            let previous = tokens
+                .iter()
+                .find(|(_, t)| *t == token)
+                .map(|(c, _)| *c)
+                .ok_or(ServerError::BadResumeToken)?;
+
+            if previous != conn {
+                if self.clients.contains(&previous) {
+                    return Err(ServerError::ConnectionActive);
+                }
+
+                tokens.remove(&previous);
+                tokens.insert(conn, *token);
+
+                let mut addresses = self.addresses.lock().unwrap();
+                if let Some(address) = addresses.remove(&previous) {
+                    addresses.insert(conn, address);
+                }
+
+                let mut counts = self.group_counts.lock().unwrap();
+                if let Some(count) = counts.remove(&previous) {
+                    counts.insert(conn, count);
+                }
+            }
+            previous
+        };
+
+        let mut groups = Vec::new();
+        for group in self.groups.all() {
+            let mut group = group.lock().await;
+            let group = &mut *group;
+            if previous != conn {
+                if let Some(index) =
+                    group.clients.iter().position(|c| *c == previous)
+                {
+                    self.changes.changed(group.uuid);
+                    group.clients[index] = conn;
+                    if let Some(member_id) = group.members.remove(&previous) {
+                        group.members.insert(conn, member_id);
+                    }
+                }
+
+                for session in group.sessions.values_mut() {
+                    for signup in session.party_signups.iter_mut() {
+                        if signup.1 == previous {
+                            self.changes.changed(group.uuid);
+                            signup.1 = conn;
+                        }
+                    }
+                }
+            }
+
+            if group.clients.contains(&conn) {
+                groups.push(group.uuid);
+            }
+        }
+        Ok(groups)
+    }
 }
 
 /// Notification sent by the server to multiple connected clients.
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub enum Notification {
     /// Indicates that the response should be ignored
     /// and no notification messages should be sent.
@@ -248,6 +1047,7 @@ pub enum Notification {
     /// notifications; before a threshold has been reached
     /// we want to return a response but not actually send
     /// any notifications.
+    #[default]
     Noop,
 
     /// Sends the response to all clients in the group.
@@ -281,131 +1081,586 @@ pub enum Notification {
     },
 }
 
-impl Default for Notification {
-    fn default() -> Self {
-        Self::Noop
-    }
-}
-
 /// MPC websocket server handling JSON-RPC requests.
-pub struct Server;
+pub struct Server {
+    options: ServerOptions,
+    state: Arc<State>,
+    started: Instant,
+}
 
 impl Server {
+    /// Create a new server.
+    ///
+    /// Logs are emitted using the [tracing](https://docs.rs/tracing)
+    /// library, unless disabled in the options the global subscriber
+    /// is initialized and in release mode the logs are formatted as JSON.
+    pub fn new(options: ServerOptions) -> Result<Self> {
+        if options.tracing {
+            // Filter traces based on the RUST_LOG env var.
+            let filter = std::env::var("RUST_LOG").unwrap_or_else(|_| {
+                "tracing=info,warp=debug,mpc_websocket=info".to_owned()
+            });
+
+            // Ignore the error when a global subscriber has already
+            // been set, for example by a previous server.
+            if cfg!(debug_assertions) {
+                let _ = tracing_subscriber::fmt()
+                    .with_env_filter(filter)
+                    .with_span_events(FmtSpan::CLOSE)
+                    .try_init();
+            } else {
+                let _ = tracing_subscriber::fmt()
+                    .with_env_filter(filter)
+                    .with_span_events(FmtSpan::CLOSE)
+                    .json()
+                    .try_init();
+            }
+        }
+
+        let mut options = options;
+        if let Some(static_files) = options.static_files.take() {
+            if !static_files.is_dir() {
+                return Err(ServerError::NotDirectory(static_files));
+            }
+            let static_files = static_files.canonicalize()?;
+            let static_path = static_files.to_string_lossy().into_owned();
+            tracing::info!(%static_path);
+            options.static_files = Some(static_files);
+        }
+
+        if options.limits.send_queue_capacity == 0 {
+            return Err(ServerError::ZeroSendQueueCapacity);
+        }
+
+        if options.expiry.interval.is_zero() {
+            return Err(ServerError::ZeroExpiryInterval);
+        }
+
+        if options.heartbeat.interval == Some(Duration::ZERO) {
+            return Err(ServerError::ZeroHeartbeatInterval);
+        }
+
+        if options.heartbeat.timeout == Some(Duration::ZERO) {
+            return Err(ServerError::ZeroIdleTimeout);
+        }
+
+        // Replies to pings would not arrive before the connection
+        // is considered idle
+        if let (Some(interval), Some(timeout)) =
+            (options.heartbeat.interval, options.heartbeat.timeout)
+        {
+            if timeout <= interval {
+                return Err(ServerError::IdleTimeoutTooShort);
+            }
+        }
+
+        if let Some(tls) = &options.tls {
+            let mut files = vec![&tls.cert, &tls.key];
+            match &tls.client_auth {
+                Some(ClientAuth::Optional(path))
+                | Some(ClientAuth::Required(path)) => files.push(path),
+                None => {}
+            }
+            for file in files {
+                if !file.is_file() {
+                    return Err(ServerError::NotFile(file.clone()));
+                }
+            }
+        }
+
+        let path = &options.path;
+        tracing::info!(%path);
+
+        let groups = Groups::default();
+        for record in options.storage.load()? {
+            groups.insert(Group::from(record));
+        }
+        if !groups.is_empty() {
+            tracing::info!(groups = groups.len(), "loaded groups");
+        }
+
+        let node_id = Uuid::new_v4();
+        let conn_prefix = if options.relay.is_some() {
+            relay::connection_prefix(&node_id)
+        } else {
+            0
+        };
+
+        let state = Arc::new(State {
+            groups,
+            options: options.clone(),
+            node_id,
+            conn_prefix,
+            ..Default::default()
+        });
+
+        Ok(Self {
+            options,
+            state,
+            started: Instant::now(),
+        })
+    }
+
     /// Start the server.
     ///
     /// The websocket endpoint is mounted at `path`,
     /// the server will bind to `addr` and static assets
     /// are served from `static_files`.
-    ///
-    /// Logs are emitted using the [tracing](https://docs.rs/tracing)
-    /// library, in release mode the logs are formatted as JSON.
     pub async fn start(
         path: &'static str,
         addr: impl Into<SocketAddr>,
         static_files: PathBuf,
     ) -> Result<()> {
-        // Filter traces based on the RUST_LOG env var.
-        let filter = std::env::var("RUST_LOG").unwrap_or_else(|_| {
-            "tracing=info,warp=debug,mpc_websocket=info".to_owned()
-        });
+        let options = ServerOptions::new(path).static_files(static_files);
+        Server::new(options)?.run(addr).await
+    }
+
+    /// Shared state for the server.
+    pub fn state(&self) -> Arc<State> {
+        Arc::clone(&self.state)
+    }
+
+    /// Composed filter for the server routes.
+    ///
+    /// Use this to mount the server inside another warp application.
+    pub fn routes(&self) -> BoxedFilter<(Box<dyn Reply>,)> {
+        let state = Arc::clone(&self.state);
+        let state = warp::any().map(move || Arc::clone(&state));
+        let limits = self.options.limits.clone();
+
+        let websocket = warp::path(self.options.path.clone())
+            .and(warp::ws())
+            .and(warp::addr::remote())
+            .and(warp::query::<Connect>())
+            .and(warp::header::optional::<String>("host"))
+            .and(state)
+            .map(
+                move |ws: warp::ws::Ws,
+                      addr: Option<SocketAddr>,
+                      connect: Connect,
+                      host: Option<String>,
+                      state| {
+                    let mut ws = ws;
+                    if let Some(max) = limits.max_message_size {
+                        ws = ws.max_message_size(max);
+                    }
+                    if let Some(max) = limits.max_frame_size {
+                        ws = ws.max_frame_size(max);
+                    }
+                    let ip = addr.map(|addr| addr.ip());
+                    let origin =
+                        host.unwrap_or_else(|| DEFAULT_ORIGIN.to_owned());
+                    let reply = ws.on_upgrade(move |socket| {
+                        client_connected(
+                            socket,
+                            ip,
+                            origin,
+                            connect.protocol,
+                            state,
+                        )
+                    });
+                    Box::new(reply) as Box<dyn Reply>
+                },
+            )
+            .boxed();
+
+        // Liveness always succeeds whilst readiness
+        // fails when the server is draining connections
+        let health = {
+            let state = Arc::clone(&self.state);
+            let started = self.started;
+            warp::path("healthz")
+                .and(warp::path::end())
+                .and(warp::get())
+                .then(move || {
+                    let state = Arc::clone(&state);
+                    async move {
+                        let health = state.health(started.elapsed()).await;
+                        Box::new(warp::reply::json(&health)) as Box<dyn Reply>
+                    }
+                })
+        };
+        let ready = {
+            let state = Arc::clone(&self.state);
+            let started = self.started;
+            warp::path("readyz")
+                .and(warp::path::end())
+                .and(warp::get())
+                .then(move || {
+                    let state = Arc::clone(&state);
+                    async move {
+                        let health = state.health(started.elapsed()).await;
+                        let status = if health.ready {
+                            StatusCode::OK
+                        } else {
+                            StatusCode::SERVICE_UNAVAILABLE
+                        };
+                        let reply = warp::reply::with_status(
+                            warp::reply::json(&health),
+                            status,
+                        );
+                        Box::new(reply) as Box<dyn Reply>
+                    }
+                })
+        };
+        let websocket = websocket.or(health).unify().or(ready).unify().boxed();
+
+        let websocket = if self.options.metrics {
+            let state = Arc::clone(&self.state);
+            let metrics = warp::path("metrics")
+                .and(warp::path::end())
+                .and(warp::get())
+                .then(move || {
+                    let state = Arc::clone(&state);
+                    async move {
+                        let body = state.metrics.encode(&state).await;
+                        let reply = warp::reply::with_header(
+                            body,
+                            "Content-Type",
+                            "text/plain; version=0.0.4",
+                        );
+                        Box::new(reply) as Box<dyn Reply>
+                    }
+                });
+            websocket.or(metrics).unify().boxed()
+        } else {
+            websocket
+        };
+
+        let routes = if let Some(static_files) = &self.options.static_files {
+            let client = warp::any()
+                .and(warp::fs::dir(static_files.clone()))
+                .map(|file| Box::new(file) as Box<dyn Reply>);
+            websocket.or(client).unify().boxed()
+        } else {
+            websocket
+        };
+
+        routes
+            .with(warp::reply::with::headers(self.options.headers.clone()))
+            .with(warp::trace::request())
+            .map(|reply| Box::new(reply) as Box<dyn Reply>)
+            .boxed()
+    }
+
+    /// Future that periodically removes expired groups and sessions.
+    ///
+    /// This is spawned automatically by [Server::run](Server::run),
+    /// when mounting the [routes](Server::routes) in another application
+    /// the caller should spawn this future.
+    pub fn reaper(&self) -> impl std::future::Future<Output = ()> {
+        let state = Arc::clone(&self.state);
+        let interval = self.options.expiry.interval;
+        async move {
+            let mut interval = tokio::time::interval(interval);
+            loop {
+                interval.tick().await;
+                reap_expired(&state).await;
+            }
+        }
+    }
 
-        if cfg!(debug_assertions) {
-            tracing_subscriber::fmt()
-                .with_env_filter(filter)
-                .with_span_events(FmtSpan::CLOSE)
-                .init();
+    /// Future that applies changes published by other server
+    /// instances to the relay.
+    ///
+    /// Completes immediately when no relay backend is configured.
+    ///
+    /// This is spawned automatically by [Server::run](Server::run),
+    /// when mounting the [routes](Server::routes) in another application
+    /// the caller should spawn this future.
+    pub fn relay(&self) -> impl std::future::Future<Output = ()> {
+        let state = Arc::clone(&self.state);
+        let backend = self.options.relay.clone();
+        async move {
+            let backend = match backend {
+                Some(backend) => backend,
+                None => return,
+            };
+            loop {
+                match backend.subscribe().await {
+                    Ok(mut subscription) => {
+                        tracing::info!("relay subscribed");
+                        // Changes may have been missed whilst the
+                        // subscription was lost
+                        for group in state.groups.all() {
+                            state.changes.changed(group.lock().await.uuid);
+                        }
+                        relay::publish_changes(&state).await;
+                        while let Some(payload) = subscription.recv().await {
+                            relay::receive(&state, &payload).await;
+                        }
+                        tracing::warn!("relay subscription lost");
+                    }
+                    Err(e) => {
+                        tracing::error!(?e, "relay subscribe failed");
+                    }
+                }
+                tokio::time::sleep(RELAY_RETRY_INTERVAL).await;
+            }
+        }
+    }
+
+    /// Run the server bound to `addr`.
+    ///
+    /// When TLS options have been configured the server
+    /// accepts secure connections (`https://` and `wss://`).
+    ///
+    /// The server is shut down gracefully when the process
+    /// receives a SIGINT or SIGTERM signal.
+    pub async fn run(self, addr: impl Into<SocketAddr>) -> Result<()> {
+        self.run_until(addr, shutdown_signal()).await
+    }
+
+    /// Run the server bound to `addr` until `signal` completes
+    /// and then shut down gracefully.
+    pub async fn run_until(
+        self,
+        addr: impl Into<SocketAddr>,
+        signal: impl Future<Output = ()> + Send + 'static,
+    ) -> Result<()> {
+        tokio::task::spawn(self.reaper());
+        tokio::task::spawn(self.relay());
+
+        let addr = addr.into();
+        let state = Arc::clone(&self.state);
+        let timeout = self.options.shutdown_timeout;
+        let signal = async move {
+            signal.await;
+            shutdown(&state, timeout).await;
+        };
+
+        let server = warp::serve(self.routes());
+        if let Some(tls) = &self.options.tls {
+            let tls_server =
+                server.tls().cert_path(&tls.cert).key_path(&tls.key);
+            let tls_server = match &tls.client_auth {
+                Some(ClientAuth::Optional(path)) => {
+                    tls_server.client_auth_optional_path(path)
+                }
+                Some(ClientAuth::Required(path)) => {
+                    tls_server.client_auth_required_path(path)
+                }
+                None => tls_server,
+            };
+            tracing::info!("tls enabled");
+            let (_, server) =
+                tls_server.bind_with_graceful_shutdown(addr, signal);
+            server.await;
         } else {
-            tracing_subscriber::fmt()
-                .with_env_filter(filter)
-                .with_span_events(FmtSpan::CLOSE)
-                .json()
-                .init();
-        }
-
-        let state = Arc::new(RwLock::new(State {
-            clients: HashMap::new(),
-            groups: Default::default(),
-        }));
-        let state = warp::any().map(move || state.clone());
-
-        if !static_files.is_dir() {
-            return Err(ServerError::NotDirectory(static_files));
-        }
-
-        let static_files = static_files.canonicalize()?;
-        let static_path = static_files.to_string_lossy().into_owned();
-        tracing::info!(%static_path);
-        tracing::info!(path);
-
-        let client = warp::any().and(warp::fs::dir(static_files));
-
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            "Cross-Origin-Embedder-Policy",
-            HeaderValue::from_static("require-corp"),
-        );
-        headers.insert(
-            "Cross-Origin-Opener-Policy",
-            HeaderValue::from_static("same-origin"),
-        );
-
-        let websocket = warp::path(path).and(warp::ws()).and(state).map(
-            |ws: warp::ws::Ws, state| {
-                ws.on_upgrade(move |socket| client_connected(socket, state))
-            },
-        );
-
-        let routes = websocket
-            .or(client)
-            .with(warp::reply::with::headers(headers))
-            .with(warp::trace::request());
-
-        warp::serve(routes).run(addr).await;
+            let (_, server) = server.bind_with_graceful_shutdown(addr, signal);
+            server.await;
+        }
+        tracing::info!("shutdown complete");
         Ok(())
     }
+
+    /// Shut down the server gracefully.
+    ///
+    /// Creating groups and sessions is rejected, a `serverShutdown`
+    /// event is sent to all clients and active sessions are given
+    /// time to finish before the connections are closed.
+    ///
+    /// This is called automatically by [Server::run](Server::run),
+    /// when mounting the [routes](Server::routes) in another application
+    /// the caller should await this before exiting.
+    pub async fn shutdown(&self) {
+        shutdown(&self.state, self.options.shutdown_timeout).await
+    }
+}
+
+/// Query parameters for the websocket endpoint.
+#[derive(Deserialize)]
+struct Connect {
+    /// Protocol version requested by the client.
+    protocol: Option<u32>,
 }
 
-async fn client_connected(ws: WebSocket, state: Arc<RwLock<State>>) {
-    let conn_id = CONNECTION_ID.fetch_add(1, Ordering::Relaxed);
+/// Send an error response with the close connection data
+/// to a client and close the websocket.
+async fn reject(mut ws: WebSocket, error: ServerError) {
+    let error =
+        RpcError::new(error.to_string(), Some(CLOSE_CONNECTION.to_string()));
+    let value = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": null,
+        "error": error,
+    });
+    let closed = async {
+        ws.send(Message::text(value.to_string())).await?;
+        ws.close().await
+    };
+    if let Err(e) = closed.await {
+        tracing::warn!(?e, "failed to close websocket")
+    }
+}
+
+async fn client_connected(
+    ws: WebSocket,
+    ip: Option<IpAddr>,
+    origin: String,
+    protocol: Option<u32>,
+    state: Arc<State>,
+) {
+    // Refuse new connections whilst shutting down
+    if state.is_draining() {
+        if let Err(e) = ws.close().await {
+            tracing::warn!(?e, "failed to close websocket")
+        }
+        return;
+    }
+
+    // Refuse clients that require a different protocol version
+    if let Some(protocol) = protocol {
+        if protocol != PROTOCOL_VERSION {
+            tracing::warn!(protocol, "unsupported protocol version");
+            let error =
+                ServerError::ProtocolVersion(protocol, PROTOCOL_VERSION);
+            reject(ws, error).await;
+            return;
+        }
+    }
+
+    // Refuse connections from addresses with too many connections
+    if let Some(ip) = ip {
+        if !state.connect_ip(ip) {
+            tracing::warn!(%ip, "too many connections");
+            reject(ws, ServerError::TooManyConnections(ip)).await;
+            return;
+        }
+    }
+
+    let conn_id =
+        state.conn_prefix | CONNECTION_ID.fetch_add(1, Ordering::Relaxed);
 
     tracing::info!(conn_id, "connected");
 
     // Split the socket into a sender and receive of messages.
     let (mut user_ws_tx, mut user_ws_rx) = ws.split();
 
-    // Use an unbounded channel to handle buffering and flushing of messages
-    // to the websocket.
-    let (tx, rx) = mpsc::unbounded_channel::<Message>();
-    let mut rx = UnboundedReceiverStream::new(rx);
+    // Use a bounded channel to handle buffering and flushing of messages
+    // to the websocket so that slow clients cannot exhaust memory.
+    let capacity = state.options.limits.send_queue_capacity;
+    let (tx, mut rx) = mpsc::channel::<Message>(capacity);
+    let (close, close_rx) = watch::channel(Close::Open);
+    let mut writer_close_rx = close_rx.clone();
+    let mut close_rx = close_rx;
 
     let mut close_flag = Arc::new(RwLock::new(false));
     let should_close = Arc::clone(&close_flag);
 
-    tokio::task::spawn(async move {
-        while let Some(message) = rx.next().await {
-            user_ws_tx
-                .send(message)
-                .unwrap_or_else(|e| {
-                    tracing::error!(?e, "websocket send error");
-                })
-                .await;
+    let writer = tokio::task::spawn(async move {
+        let reason = tokio::select! {
+            _ = async {
+                while let Some(message) = rx.recv().await {
+                    user_ws_tx
+                        .send(message)
+                        .unwrap_or_else(|e| {
+                            tracing::error!(?e, "websocket send error");
+                        })
+                        .await;
+
+                    let reader = should_close.read().await;
+                    if *reader {
+                        if let Err(e) = user_ws_tx.close().await {
+                            tracing::warn!(?e, "failed to close websocket")
+                        }
+                        break;
+                    }
+                }
+            } => return,
+            reason = closed(&mut writer_close_rx) => reason,
+        };
 
-            let reader = should_close.read().await;
-            if *reader {
-                match user_ws_tx.close().await {
-                    Err(e) => tracing::warn!(?e, "failed to close websocket"),
-                    _ => {}
+        let close = async {
+            match reason {
+                // Queued messages are discarded, try to inform the client
+                // why it is being disconnected
+                Close::Evicted => {
+                    let error = RpcError::new(
+                        ServerError::SendQueueFull(conn_id).to_string(),
+                        Some(CLOSE_CONNECTION.to_string()),
+                    );
+                    let value = serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "id": null,
+                        "error": error,
+                    });
+                    user_ws_tx.send(Message::text(value.to_string())).await?;
+                }
+                // Flush queued messages before closing
+                Close::Shutdown | Close::Open => {
+                    while let Ok(message) = rx.try_recv() {
+                        user_ws_tx.send(message).await?;
+                    }
                 }
-                break;
+            }
+            user_ws_tx.close().await
+        };
+
+        match tokio::time::timeout(CLOSE_TIMEOUT, close).await {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => {
+                tracing::warn!(conn_id, ?e, "failed to close websocket")
+            }
+            Err(_) => {
+                tracing::warn!(conn_id, "timed out closing websocket")
             }
         }
     });
 
-    // Save the sender in our list of connected clients.
-    state.write().await.clients.insert(conn_id, tx);
-
-    // Handle incoming requests from clients
-    while let Some(result) = user_ws_rx.next().await {
+    // Save the sender in our list of connected clients
+    // and issue an authentication challenge.
+    let challenge = auth::challenge(&origin);
+    state.clients.insert(
+        conn_id,
+        ClientSender {
+            tx,
+            capacity,
+            close,
+        },
+    );
+    state
+        .challenges
+        .lock()
+        .unwrap()
+        .insert(conn_id, challenge.clone());
+
+    let response: Response = Event::ConnectionChallenge(challenge).into();
+    rpc_response(conn_id, &response, &state).await;
+
+    // Handle incoming requests from clients until the
+    // client disconnects or the server closes the connection
+    let mut rate_limit =
+        state.options.limits.requests_per_second.map(RateLimit::new);
+    let heartbeat = state.options.heartbeat.clone();
+    let mut ping = heartbeat.interval.map(|period| {
+        tokio::time::interval_at(tokio::time::Instant::now() + period, period)
+    });
+    let mut last_seen = tokio::time::Instant::now();
+    let mut closed_by_server = false;
+    loop {
+        let idle = heartbeat.timeout.map(|timeout| last_seen + timeout);
+        let result = tokio::select! {
+            result = user_ws_rx.next() => result,
+            _ = tick(&mut ping) => {
+                if let Some(client) = state.clients.get(&conn_id) {
+                    client.send(conn_id, Message::ping(Vec::new()), &state);
+                }
+                continue;
+            }
+            _ = deadline(idle) => {
+                tracing::info!(conn_id, "idle timeout");
+                break;
+            }
+            _ = closed(&mut close_rx) => {
+                closed_by_server = true;
+                break;
+            }
+        };
+        let result = match result {
+            Some(result) => result,
+            None => break,
+        };
         let msg = match result {
             Ok(msg) => msg,
             Err(e) => {
@@ -413,20 +1668,107 @@ async fn client_connected(ws: WebSocket, state: Arc<RwLock<State>>) {
                 break;
             }
         };
+        last_seen = tokio::time::Instant::now();
+
+        client_incoming_message(
+            conn_id,
+            &mut close_flag,
+            &mut rate_limit,
+            msg,
+            &state,
+        )
+        .await;
+    }
 
-        client_incoming_message(conn_id, &mut close_flag, msg, &state).await;
+    // Wait for the websocket to be closed
+    if closed_by_server {
+        let _ = writer.await;
     }
 
     // user_ws_rx stream will keep processing as long as the user stays
     // connected. Once they disconnect, then...
     client_disconnected(conn_id, &state).await;
+
+    if let Some(ip) = ip {
+        state.disconnect_ip(ip);
+    }
+}
+
+/// Token bucket that limits the rate of requests for a connection.
+struct RateLimit {
+    rate: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimit {
+    /// Create a rate limit allowing `rate` requests per second.
+    fn new(rate: u32) -> Self {
+        Self {
+            rate: rate as f64,
+            tokens: rate as f64,
+            updated: Instant::now(),
+        }
+    }
+
+    /// Take a token for a request.
+    ///
+    /// Returns `false` when the rate limit has been exceeded.
+    fn take(&mut self) -> bool {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.updated = now;
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Wait for the next tick of an optional interval.
+///
+/// Never completes if there is no interval.
+async fn tick(interval: &mut Option<tokio::time::Interval>) {
+    match interval {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => futures_util::future::pending().await,
+    }
+}
+
+/// Wait until an optional deadline.
+///
+/// Never completes if there is no deadline.
+async fn deadline(deadline: Option<tokio::time::Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => futures_util::future::pending().await,
+    }
+}
+
+/// Wait until the server closes a connection.
+///
+/// Never completes if the client is removed without being closed.
+async fn closed(rx: &mut watch::Receiver<Close>) -> Close {
+    while rx.changed().await.is_ok() {
+        let reason = *rx.borrow();
+        if reason != Close::Open {
+            return reason;
+        }
+    }
+    futures_util::future::pending().await
 }
 
 async fn client_incoming_message(
     conn_id: usize,
     close_flag: &mut Arc<RwLock<bool>>,
+    rate_limit: &mut Option<RateLimit>,
     msg: Message,
-    state: &Arc<RwLock<State>>,
+    state: &Arc<State>,
 ) {
     let msg = if let Ok(s) = msg.to_str() {
         s
@@ -434,6 +1776,29 @@ async fn client_incoming_message(
         return;
     };
 
+    if let Some(max) = state.options.limits.max_request_size {
+        if msg.len() > max {
+            tracing::warn!(conn_id, size = msg.len(), "request too large");
+            let error = ServerError::RequestTooLarge(max);
+            let response: Response =
+                json_rpc2::Error::from(Box::from(error)).into();
+            rpc_response(conn_id, &response, state).await;
+            return;
+        }
+    }
+
+    // Messages that cannot be parsed also count towards the rate limit
+    if let Some(rate_limit) = rate_limit {
+        if !rate_limit.take() {
+            tracing::warn!(conn_id, "rate limited");
+            let error = ServerError::RateLimited(conn_id);
+            let response: Response =
+                json_rpc2::Error::from(Box::from(error)).into();
+            rpc_response(conn_id, &response, state).await;
+            return;
+        }
+    }
+
     match json_rpc2::from_str(msg) {
         Ok(req) => rpc_request(conn_id, close_flag, req, state).await,
         Err(e) => tracing::warn!(conn_id, ?e, "websocket rx JSON error"),
@@ -445,23 +1810,16 @@ async fn rpc_request(
     conn_id: usize,
     close_flag: &mut Arc<RwLock<bool>>,
     request: Request,
-    state: &Arc<RwLock<State>>,
+    state: &Arc<State>,
 ) {
     use json_rpc2::futures::*;
 
-    let service: Box<
-        dyn Service<
-            Data = (
-                usize,
-                Arc<RwLock<State>>,
-                Arc<Mutex<Option<Notification>>>,
-            ),
-        >,
-    > = Box::new(ServiceHandler {});
+    let service: Box<dyn Service<Data = <ServiceHandler as Service>::Data>> =
+        Box::new(ServiceHandler {});
     let server = Server::new(vec![&service]);
 
-    let notification: Arc<Mutex<Option<Notification>>> =
-        Arc::new(Mutex::new(None));
+    let notification: Arc<Mutex<Vec<Notification>>> =
+        Arc::new(Mutex::new(Vec::new()));
 
     if let Some(response) = server
         .serve(
@@ -482,8 +1840,10 @@ async fn rpc_request(
         }
     }
 
-    let mut writer = notification.lock().await;
-    if let Some(notification) = writer.take() {
+    relay::publish_changes(state).await;
+
+    let notifications = std::mem::take(&mut *notification.lock().await);
+    for notification in notifications {
         rpc_notify(state, notification).await;
     }
 }
@@ -504,16 +1864,15 @@ fn filter_clients(
 }
 
 /// Send notification to connected client(s).
-async fn rpc_notify(state: &Arc<RwLock<State>>, notification: Notification) {
-    let reader = state.read().await;
+pub(crate) async fn rpc_notify(state: &Arc<State>, notification: Notification) {
     match notification {
         Notification::Group {
             group_id,
             filter,
             response,
         } => {
-            let clients = if let Some(group) = reader.groups.get(&group_id) {
-                group.clients.clone()
+            let clients = if let Some(group) = state.groups.get(&group_id) {
+                group.lock().await.clients.clone()
             } else {
                 vec![0usize]
             };
@@ -529,9 +1888,10 @@ async fn rpc_notify(state: &Arc<RwLock<State>>, notification: Notification) {
             filter,
             response,
         } => {
-            let clients = if let Some(group) = reader.groups.get(&group_id) {
+            let clients = if let Some(group) = state.groups.get(&group_id) {
+                let group = group.lock().await;
                 if let Some(session) = group.sessions.get(&session_id) {
-                    session.party_signups.iter().map(|i| i.1.clone()).collect()
+                    session.party_signups.iter().map(|i| i.1).collect()
                 } else {
                     tracing::warn!(
                         %session_id,
@@ -560,51 +1920,296 @@ async fn rpc_notify(state: &Arc<RwLock<State>>, notification: Notification) {
 async fn rpc_response(
     conn_id: usize,
     response: &json_rpc2::Response,
-    state: &Arc<RwLock<State>>,
+    state: &Arc<State>,
 ) {
     tracing::debug!(conn_id, "send message");
-    if let Some(tx) = state.read().await.clients.get(&conn_id) {
-        tracing::debug!(?response, "send response");
-        let msg = serde_json::to_string(response).unwrap();
-        if let Err(_disconnected) = tx.send(Message::text(msg)) {
-            // The tx is disconnected, our `client_disconnected` code
-            // should be happening in another task, nothing more to
-            // do here.
-        }
+    tracing::debug!(?response, "send response");
+    let msg = serde_json::to_string(response).unwrap();
+    if let Some(client) = state.clients.get(&conn_id) {
+        client.send(conn_id, Message::text(msg), state);
+        return;
+    }
+
+    // Connection may belong to another server instance
+    if let Some(backend) = &state.options.relay {
+        let envelope = relay::Envelope::Deliver {
+            conn_id,
+            message: msg,
+        };
+        relay::publish(backend, state.node_id, envelope).await;
     } else {
         tracing::warn!(conn_id, "could not find tx for websocket");
     }
 }
 
-async fn client_disconnected(conn_id: usize, state: &Arc<RwLock<State>>) {
+async fn client_disconnected(conn_id: usize, state: &Arc<State>) {
     tracing::info!(conn_id, "disconnected");
 
-    // FIXME: prune session party signups for disconnected clients?
+    // Stream closed up, so remove from the client list
+    state.clients.remove(&conn_id);
+    state.challenges.lock().unwrap().remove(&conn_id);
+
+    let token = state.tokens.lock().unwrap().get(&conn_id).copied();
+    let grace_period = state.options.resume_grace_period;
+
+    // Clients that were issued a resume token may reconnect
+    // within the grace period so defer pruning the connection,
+    // new connections are refused when shutting down
+    if let Some(token) = token {
+        if !grace_period.is_zero() && !state.is_draining() {
+            let state = Arc::clone(state);
+            tokio::task::spawn(async move {
+                tokio::time::sleep(grace_period).await;
+                let expired = {
+                    let mut tokens = state.tokens.lock().unwrap();
+                    if tokens.get(&conn_id) == Some(&token) {
+                        tokens.remove(&conn_id);
+                        true
+                    } else {
+                        false
+                    }
+                };
+                if expired {
+                    tracing::info!(conn_id, "resume token expired");
+                    prune_connection(conn_id, &state).await;
+                }
+            });
+            return;
+        }
+        state.tokens.lock().unwrap().remove(&conn_id);
+    }
 
-    let mut empty_groups: Vec<Uuid> = Vec::new();
-    {
-        let mut writer = state.write().await;
-        // Stream closed up, so remove from the client list
-        writer.clients.remove(&conn_id);
-        // Remove the connection from any client groups
-        for (key, group) in writer.groups.iter_mut() {
-            if let Some(index) =
-                group.clients.iter().position(|x| *x == conn_id)
-            {
-                group.clients.remove(index);
+    prune_connection(conn_id, state).await;
+}
+
+/// Remove a connection from all groups and sessions.
+async fn prune_connection(conn_id: usize, state: &Arc<State>) {
+    let mut departed: Vec<(Uuid, Uuid, u16)> = Vec::new();
+    let mut closed: Vec<Notification> = Vec::new();
+    let mut left: Vec<Notification> = Vec::new();
+    state.addresses.lock().unwrap().remove(&conn_id);
+
+    // Remove the connection from any client groups
+    for group in state.groups.all() {
+        let mut group = group.lock().await;
+        let group = &mut *group;
+        let key = group.uuid;
+
+        // Prune party signups so the slots are freed
+        let (member, removed) = group.remove_connection(conn_id);
+        if member.is_some() {
+            state.leave_group(conn_id);
+        }
+        if member.is_some() || !removed.is_empty() {
+            state.changes.changed(key);
+        }
+        for (session_id, party_number) in &removed {
+            departed.push((key, *session_id, *party_number));
+        }
+
+        // Prune groups with no more connected clients
+        if member.is_some() && group.clients.is_empty() {
+            state.remove_group(group);
+            tracing::info!(%key, "removed group");
+            continue;
+        }
+
+        // Remaining participants may all have finished
+        for (session_id, _) in &removed {
+            if let Some(session) = group.sessions.get_mut(session_id) {
+                closed.extend(session_closed_notification(state, key, session));
+            }
+        }
+        if member.is_some() || !removed.is_empty() {
+            if let Err(e) = state.save_group(group) {
+                tracing::error!(%key, ?e, "failed to save group");
             }
+        }
+
+        if let Some(member_id) = member {
+            left.push(member_notification(
+                Event::GroupMemberLeft,
+                conn_id,
+                member_id,
+                group,
+            ));
+        }
+    }
+
+    relay::publish_changes(state).await;
+
+    // Notify remaining session participants
+    for (group_id, session_id, party_number) in departed {
+        tracing::info!(%session_id, party_number, "session participant left");
+        let notification =
+            participant_left_notification(group_id, session_id, party_number);
+        rpc_notify(state, notification).await;
+    }
+
+    // Notify sessions that were closed by the departure
+    for notification in closed {
+        rpc_notify(state, notification).await;
+    }
 
-            // Group has no more connected clients so flag it for removal
-            if group.clients.is_empty() {
-                empty_groups.push(key.clone());
+    // Notify remaining group members
+    for notification in left {
+        rpc_notify(state, notification).await;
+    }
+}
+
+/// Remove expired groups and sessions.
+///
+/// A `sessionExpired` event is sent to the connected clients
+/// of each session that is removed.
+async fn reap_expired(state: &Arc<State>) {
+    let mut expired: Vec<(Uuid, Vec<usize>)> = Vec::new();
+    let expiry = &state.options.expiry;
+
+    for group in state.groups.all() {
+        let mut group = group.lock().await;
+        let group = &mut *group;
+        let group_id = group.uuid;
+
+        let num_sessions = group.sessions.len();
+        group.sessions.retain(|session_id, session| {
+            if session.is_expired(expiry) {
+                let clients = session
+                    .party_signups
+                    .iter()
+                    .map(|(_, c)| *c)
+                    .collect::<Vec<usize>>();
+                expired.push((*session_id, clients));
+                tracing::info!(%session_id, "removed expired session");
+                false
+            } else {
+                true
+            }
+        });
+
+        let idle = expiry
+            .group
+            .map(|ttl| group.last_activity.elapsed() > ttl)
+            .unwrap_or(false);
+        let active = group.sessions.values().any(|s| s.closed.is_none());
+        // Clients of other instances are not known to this instance,
+        // the owning instance removes the group when they disconnect
+        let connected = group.clients.iter().any(|c| {
+            !relay::is_owner(state.conn_prefix, *c) || state.clients.contains(c)
+        });
+        if idle && !active && !connected {
+            for session_id in group.sessions.keys() {
+                expired.push((*session_id, group.clients.clone()));
+            }
+            tracing::info!(%group_id, "removed expired group");
+            state.remove_group(group);
+        } else if group.sessions.len() != num_sessions {
+            state.changes.changed(group_id);
+            if let Err(e) = state.save_group(group) {
+                tracing::error!(%group_id, ?e, "failed to save group");
             }
         }
     }
 
-    // Prune empty groups
-    let mut writer = state.write().await;
-    for key in empty_groups {
-        writer.groups.remove(&key);
-        tracing::info!(%key, "removed group");
+    for (_, clients) in expired.iter_mut() {
+        clients.retain(|c| state.clients.contains(c));
+    }
+
+    relay::publish_changes(state).await;
+
+    for (session_id, clients) in expired {
+        let event = Event::SessionExpired(session_id);
+        let messages = clients
+            .into_iter()
+            .map(|conn_id| {
+                let response: Response = event.clone().into();
+                (conn_id, response)
+            })
+            .collect::<Vec<_>>();
+        rpc_notify(state, Notification::Relay { messages }).await;
+    }
+}
+
+/// Wait for a SIGINT or SIGTERM signal.
+async fn shutdown_signal() {
+    let interrupt = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::warn!(?e, "failed to listen for SIGINT");
+            futures_util::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::warn!(?e, "failed to listen for SIGTERM");
+                futures_util::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = futures_util::future::pending::<()>();
+
+    tokio::select! {
+        _ = interrupt => {},
+        _ = terminate => {},
+    }
+}
+
+/// Drain active sessions and close all connections.
+async fn shutdown(state: &Arc<State>, timeout: Duration) {
+    if state.draining.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    tracing::info!(?timeout, "shutting down");
+
+    let response: Response = Event::ServerShutdown(timeout.as_secs()).into();
+    for conn_id in state.clients.ids() {
+        rpc_response(conn_id, &response, state).await;
+    }
+
+    // Wait for active sessions to be finished, new connections are
+    // refused so sessions without connected participants cannot finish
+    let deadline = Instant::now() + timeout;
+    loop {
+        let mut active = 0;
+        for group in state.groups.all() {
+            let group = group.lock().await;
+            active += group
+                .sessions
+                .values()
+                .filter(|session| {
+                    session.closed.is_none()
+                        && session
+                            .party_signups
+                            .iter()
+                            .any(|(_, conn)| state.clients.contains(conn))
+                })
+                .count();
+        }
+        if active == 0 {
+            break;
+        }
+        if Instant::now() >= deadline {
+            tracing::warn!(active, "shutdown timeout elapsed");
+            break;
+        }
+        tokio::time::sleep(SHUTDOWN_POLL_INTERVAL).await;
+    }
+
+    // Close all the connections
+    for client in state.clients.all().values() {
+        let _ = client.close.send(Close::Shutdown);
+    }
+
+    let deadline = Instant::now() + CLOSE_TIMEOUT;
+    while !state.clients.is_empty() && Instant::now() < deadline {
+        tokio::time::sleep(SHUTDOWN_POLL_INTERVAL).await;
     }
 }
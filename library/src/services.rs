@@ -4,8 +4,10 @@
 //! method's documentation for more details.
 //!
 //! Notifications sent to connected clients are sent as a tuple
-//! of `String` event name followed by an arbitrary JSON `Value`
-//! payload for the event.
+//! of `String` event name followed by the payload for the event;
+//! the events and their payloads are described by the [Event](Event)
+//! enum and the JSON Schema files in the `schema` directory which
+//! are exported using `cargo run --example schema`.
 //!
 //! ## Methods
 //!
@@ -15,9 +17,14 @@
 //!
 //! * `label`: Human-friendly `String` label for the group.
 //! * `parameters`: [Parameters](Parameters) for key generation and signing.
+//! * `allowlist`: Optional array of `String` addresses allowed to join the group.
 //!
 //! Create a new group; the client that sends this method automatically joins the group.
 //!
+//! When an `allowlist` is given the caller must be authenticated (see [Connection.authenticate](#connectionauthenticate)) and the allowlist must include the address of the caller; only connections authenticated as one of the addresses in the allowlist may join and access the group.
+//!
+//! A `connectionToken` event is emitted to the caller, see [Connection.resume](#connectionresume).
+//!
 //! Returns the UUID for the group.
 //!
 //! ### Group.join
@@ -26,8 +33,24 @@
 //!
 //! Register the calling client as a member of the group.
 //!
+//! A `groupMemberJoined` event is emitted to the other clients in the group; the payload is an object with the `groupId`, the `String` UUID `memberId` issued to the client that joined, the number of `members` in the group and the number of `parties` expected by the group [Parameters](Parameters).
+//!
+//! A `connectionToken` event is emitted to the caller, see [Connection.resume](#connectionresume).
+//!
 //! Returns the group object.
 //!
+//! ### Group.leave
+//!
+//! * `group_id`: The `String` UUID for the group.
+//!
+//! Remove the calling client from the group and from all the sessions in the group.
+//!
+//! A `groupMemberLeft` event is emitted to the remaining clients in the group; the payload is the same as for the `groupMemberJoined` event, see [Group.join](#groupjoin). A `sessionParticipantLeft` event is emitted for each party signup that was removed, see [Disconnection](#disconnection).
+//!
+//! When there are no more clients in the group the group is removed.
+//!
+//! This method is a notification and does not return anything to the caller.
+//!
 //! ### Session.create
 //! * `group_id`: The `String` UUID for the group.
 //! * `kind`: The `String` kind of session (either `keygen` or `sign`).
@@ -44,6 +67,8 @@
 //!
 //! Join an existing session.
 //!
+//! For this method and the other session methods that accept a `kind` it is an error if the `kind` does not match the kind of the session.
+//!
 //! Returns the session object.
 //!
 //! ### Session.signup
@@ -54,7 +79,13 @@
 //!
 //! Register as a co-operating party for a session.
 //!
-//! When the required number of parties have signed up to a session a `sessionSignup` event is emitted to all the clients in the session. For key generation there must be `parties` clients in the session and for signing there must be `threshold + 1` clients registered for the session.
+//! Signing up is idempotent; if the caller has already signed up to the session the existing party signup number is returned. Once the required number of parties for the session kind have signed up the session is *full* and further signups are rejected; the `full` field of the session object indicates whether a session is full.
+//!
+//! When the required number of parties have signed up to a session a `sessionSignup` event is emitted to all the clients in the session; the payload is an object with the `sessionId` and the sorted party signup numbers of the `participants`. For key generation there must be `parties` clients in the session and for signing there must be `threshold + 1` clients registered for the session.
+//!
+//! A `connectionToken` event is emitted to the caller, see [Connection.resume](#connectionresume).
+//!
+//! When multiple server instances share a relay, clients connected to different instances that sign up concurrently may be assigned the same party number; the signup from the connection with the lowest identifier is kept and a `sessionSignupRejected` event is emitted to the other client, the payload is an object with the `sessionId` and the rejected `partyNumber`. The client should sign up to the session again.
 //!
 //! Returns the party signup number.
 //!
@@ -69,7 +100,7 @@
 //!
 //! The given `number` must be in range and must be an available slot.
 //!
-//! When the required number of `parties` have been allocated to a session a `sessionLoad` event is emitted to all the clients in the session.
+//! When the required number of `parties` have been allocated to a session a `sessionLoad` event is emitted to all the clients in the session; the payload is the same as for the `sessionSignup` event, see [Session.signup](#sessionsignup).
 //!
 //! Returns the party signup number.
 //!
@@ -82,6 +113,8 @@
 //!
 //! Relay a message to all the other peers in the session (broadcast) or send directly to another peer.
 //!
+//! The caller must have signed up to the session and the `sender` field of the `message` must be the party signup number for the caller.
+//!
 //! A `message` is treated as peer to peer when the `receiver` field is present which should be the party signup `number` for the peer.
 //!
 //! This method is a notification and does not return anything to the caller.
@@ -98,6 +131,57 @@
 //!
 //! This method is a notification and does not return anything to the caller.
 //!
+//! ### Session.abort
+//!
+//! * `group_id`: The `String` UUID for the group.
+//! * `session_id`: The `String` UUID for the session.
+//! * `number`: The `u16` party signup number.
+//! * `code`: The `String` code for the reason the session is aborted.
+//! * `message`: The `String` message describing the reason the session is aborted.
+//!
+//! Abort the session for all participants, typically because the protocol failed for the calling client.
+//!
+//! The party signup `number` must belong to the caller. Once a session has been aborted further messages for the session are rejected.
+//!
+//! A `sessionAborted` event is emitted to all the clients in the session; the payload is an object with the `sessionId`, the `partyNumber` of the participant that aborted the session and the reason `code` and `message`.
+//!
+//! This method is a notification and does not return anything to the caller.
+//!
+//! ### Session.blame
+//!
+//! * `group_id`: The `String` UUID for the group.
+//! * `session_id`: The `String` UUID for the session.
+//! * `number`: The `u16` party signup number.
+//! * `blame`: Object with the `round` that failed, the `kind` of error, the party signup numbers of the accused `culprits` and an optional `message`.
+//!
+//! Report the parties that the protocol identified as the cause of a failed round, the blame information is available from the webassembly bindings when a round fails.
+//!
+//! The party signup `number` must belong to the caller and the `culprits` must be participants in the session.
+//!
+//! A `sessionBlame` event is emitted to all the clients in the group so that the accused parties may be excluded from future sessions; the payload is the `blame` object with the `sessionId` and the `partyNumber` of the participant that reported the blame.
+//!
+//! This method is a notification and does not return anything to the caller.
+//!
+//! ### Session.leave
+//!
+//! * `group_id`: The `String` UUID for the group.
+//! * `session_id`: The `String` UUID for the session.
+//!
+//! Remove the party signups for the calling client from the session so that the slots may be re-used; the caller remains a member of the group.
+//!
+//! A `sessionParticipantLeft` event is emitted to the remaining clients in the session for each party signup that was removed, see [Disconnection](#disconnection).
+//!
+//! This method is a notification and does not return anything to the caller.
+//!
+//! ### Session.participants
+//!
+//! * `group_id`: The `String` UUID for the group.
+//! * `session_id`: The `String` UUID for the session.
+//!
+//! Get the party signup numbers for a session; this is the list of participants required to create a signer.
+//!
+//! Returns an object with the sorted party signup numbers of the `participants` and the `partyNumber` for the caller which is `null` when the caller has not signed up to the session.
+//!
 //! ### Notify.proposal
 //!
 //! * `group_id`: The `String` UUID for the group.
@@ -107,6 +191,8 @@
 //!
 //! Sends a signing proposal to *all other clients in the group*. The event emitted is `notifyProposal` and the payload is an object with `sessionId`, `proposalId` and the `message` to be signed.
 //!
+//! The caller must be a member of the group.
+//!
 //! This method is a notification and does not return anything to the caller.
 //!
 //! ### Notify.signed
@@ -121,18 +207,105 @@
 //!
 //! This method is a notification and does not return anything to the caller.
 //!
+//! ### Connection.authenticate
+//!
+//! * `signature`: The `String` hex encoded signature.
+//!
+//! Authenticate the connection.
+//!
+//! When a client connects the server emits a `connectionChallenge` event to the client; the payload is a `String` challenge containing a human-readable statement that names the server origin (the `Host` of the websocket request) followed by a random nonce. To authenticate the client signs the entire challenge using Ethereum `personal_sign` (the challenge is signed as UTF-8 text) and calls this method with the 65 byte signature.
+//!
+//! The address that signed the challenge is attached to the connection and used to authorize access to groups created with an allowlist. The server may also be configured to require authentication to create or join any group.
+//!
+//! Returns the `String` address for the connection.
+//!
+//! ### Connection.resume
+//!
+//! * `token`: The `String` resume token.
+//!
+//! Resume a connection that was dropped.
+//!
+//! When a client joins a group or signs up to a session the server issues a resume token for the connection and emits a `connectionToken` event to the caller; the payload is the `String` token. The token is the same for all groups and sessions the connection belongs to.
+//!
+//! If the websocket is closed a client may reconnect and call this method with the token to rebind the group memberships and party signup numbers of the dropped connection to the new connection. Messages sent to the client whilst it was disconnected are *not* buffered and will not be delivered.
+//!
+//! Returns the UUIDs for the groups the connection belongs to.
+//!
+//! ### Connection.ping
+//!
+//! * `value`: Optional JSON value.
+//!
+//! Check the connection is alive; clients may call this method to measure the latency of the connection.
+//!
+//! Returns the `value` given by the caller or `null`.
+//!
+//! ### Server.info
+//!
+//! Get information about the server.
+//!
+//! Returns an object with the crate `version`, the `protocol` version, the names of the supported `methods` and `events` and the configured `limits`, see [Limits](#limits).
+//!
+//! ## Protocol version
+//!
+//! Clients may request a protocol version when connecting by adding a `protocol` query parameter to the websocket URL, for example `/mpc?protocol=1`. When the server does not support the requested version an error response with the `close-connection` data is sent to the client and the connection is closed.
+//!
+//! ## Heartbeat
+//!
+//! The server sends websocket pings to each connection on an interval and closes connections that have been idle for longer than a timeout; any message received from a client including the reply to a ping resets the idle time of the connection. Connections closed due to the timeout are handled in the same way as any other disconnection.
+//!
+//! ## Shutdown
+//!
+//! When the server is shutting down a `serverShutdown` event is emitted to all connected clients; the payload is the `u64` number of seconds the server will wait for active sessions to be finished before the connections are closed. Whilst the server is shutting down creating groups and sessions is rejected.
+//!
+//! ## Expiry
+//!
+//! Groups and sessions are removed by the server when they expire. Sessions that have not been finished by all participants expire after a period of time since they were created and finished sessions expire after a shorter period of time; groups expire after a period of inactivity when they do not contain any unfinished sessions and none of their members are connected.
+//!
+//! When a session expires a `sessionExpired` event is emitted to the connected clients in the session; the payload is the `String` UUID for the session.
+//!
+//! ## Limits
+//!
+//! The server may be configured to limit the size of requests, the rate of requests for each connection, the number of groups a connection may belong to, the number of sessions in a group and the number of concurrent connections from an IP address.
+//!
+//! Requests that exceed a limit are rejected with an error response; connections that exceed the limit for an IP address are sent an error response with the `close-connection` data and closed.
+//!
+//! ## Disconnection
+//!
+//! When a client that was issued a resume token disconnects it is not removed from groups and sessions until the resume grace period has elapsed.
+//!
+//! When a client disconnects the party signups for the connection are removed from every session so that the slots may be re-used. For each party number that was removed a `sessionParticipantLeft` event is emitted to the remaining clients in the session; the payload is the `u16` party number of the departed participant.
+//!
+//! When a client that is a member of a group disconnects a `groupMemberLeft` event is emitted to the remaining clients in the group, see [Group.leave](#groupleave).
+//!
+//! Messages for each client are queued by the server and clients that fall too far behind consuming messages are disconnected; before the websocket is closed the server attempts to send an error response with the `close-connection` data.
+//!
 use async_trait::async_trait;
 use json_rpc2::{futures::*, Error, Request, Response, Result, RpcError};
-use serde::{Deserialize, Serialize};
+use schemars::{
+    gen::SchemaGenerator,
+    schema::{
+        ArrayValidation, InstanceType, Metadata, Schema, SchemaObject,
+        SingleOrVec, SubschemaValidation,
+    },
+    JsonSchema,
+};
+use serde::{
+    de::{self, SeqAccess, Visitor},
+    ser::SerializeTuple,
+    Deserialize, Deserializer, Serialize,
+};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::fmt;
 use std::sync::Arc;
+use std::time::Instant;
 use thiserror::Error;
-use tokio::sync::{Mutex, RwLock};
+use tokio::sync::{Mutex, OwnedMutexGuard};
 use uuid::Uuid;
 
+use super::auth::{recover_address, Address};
+use super::options::Limits;
 use super::server::{
-    Group, Notification, Parameters, Session, SessionKind, State,
+    Abort, Group, Notification, Parameters, Session, SessionKind, State,
 };
 
 /// Error thrown by the JSON-RPC services.
@@ -154,7 +327,7 @@ pub enum ServiceError {
     #[error("group {0} does not exist")]
     GroupDoesNotExist(Uuid),
     /// Error generated when a session does not exist.
-    #[error("group {0} does not exist")]
+    #[error("session {0} does not exist")]
     SessionDoesNotExist(Uuid),
     /// Error generated when a party number does not exist.
     #[error("party {0} does not exist")]
@@ -170,8 +343,81 @@ pub enum ServiceError {
     /// the specified group.
     #[error("client {0} does not belong to the group {1}")]
     BadConnection(usize, Uuid),
+    /// Error generated when a client connection must be authenticated.
+    #[error("client {0} is not authenticated")]
+    NotAuthenticated(usize),
+    /// Error generated when a client connection is not authorized
+    /// to access a group.
+    #[error("client {0} is not authorized for the group {1}")]
+    Unauthorized(usize, Uuid),
+    /// Error generated when a group allowlist does not include
+    /// the address of the client creating the group.
+    #[error("group allowlist must include the address {0}")]
+    AllowlistCreator(Address),
+    /// Error generated when a client connection has no pending
+    /// authentication challenge.
+    #[error("client {0} does not have an authentication challenge")]
+    NoChallenge(usize),
+    /// Error generated when a client connection has not signed up
+    /// to the specified session.
+    #[error("client {0} is not a participant in the session {1}")]
+    NotParticipant(usize, Uuid),
+    /// Error generated when the sender of a message is not
+    /// the party number belonging to the caller.
+    #[error("sender {0} does not belong to the client")]
+    BadSender(u16),
+    /// Error generated when the kind supplied by a client does not
+    /// match the kind of the session.
+    #[error("session {0} is a {1} session but {2} was requested")]
+    KindMismatch(Uuid, SessionKind, SessionKind),
+    /// Error generated when a client connection belongs to
+    /// the maximum number of groups.
+    #[error("client {0} belongs to too many groups")]
+    TooManyGroups(usize),
+    /// Error generated when a group has the maximum number of sessions.
+    #[error("group {0} has too many sessions")]
+    TooManySessions(Uuid),
+    /// Error generated when a session has been aborted.
+    #[error("session {0} has been aborted")]
+    SessionAborted(Uuid),
+    /// Error generated when creating groups or sessions
+    /// whilst the server is shutting down.
+    #[error("server is shutting down")]
+    ShuttingDown,
+}
+
+impl ServiceError {
+    /// Name of the error variant.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ServiceError::PartiesTooSmall => "PartiesTooSmall",
+            ServiceError::ThresholdTooSmall => "ThresholdTooSmall",
+            ServiceError::ThresholdRange => "ThresholdRange",
+            ServiceError::GroupFull(..) => "GroupFull",
+            ServiceError::GroupDoesNotExist(..) => "GroupDoesNotExist",
+            ServiceError::SessionDoesNotExist(..) => "SessionDoesNotExist",
+            ServiceError::PartyDoesNotExist(..) => "PartyDoesNotExist",
+            ServiceError::BadParty(..) => "BadParty",
+            ServiceError::BadPeerReceiver(..) => "BadPeerReceiver",
+            ServiceError::BadConnection(..) => "BadConnection",
+            ServiceError::NotAuthenticated(..) => "NotAuthenticated",
+            ServiceError::Unauthorized(..) => "Unauthorized",
+            ServiceError::AllowlistCreator(..) => "AllowlistCreator",
+            ServiceError::NoChallenge(..) => "NoChallenge",
+            ServiceError::NotParticipant(..) => "NotParticipant",
+            ServiceError::BadSender(..) => "BadSender",
+            ServiceError::KindMismatch(..) => "KindMismatch",
+            ServiceError::TooManyGroups(..) => "TooManyGroups",
+            ServiceError::TooManySessions(..) => "TooManySessions",
+            ServiceError::SessionAborted(..) => "SessionAborted",
+            ServiceError::ShuttingDown => "ShuttingDown",
+        }
+    }
 }
 
+/// Version of the JSON-RPC protocol implemented by the services.
+pub const PROTOCOL_VERSION: u32 = 1;
+
 /// Error data indicating the connection should be closed.
 pub const CLOSE_CONNECTION: &str = "close-connection";
 
@@ -179,6 +425,8 @@ pub const CLOSE_CONNECTION: &str = "close-connection";
 pub const GROUP_CREATE: &str = "Group.create";
 /// Method to join a group.
 pub const GROUP_JOIN: &str = "Group.join";
+/// Method to leave a group.
+pub const GROUP_LEAVE: &str = "Group.leave";
 /// Method to create a session.
 pub const SESSION_CREATE: &str = "Session.create";
 /// Method to join a session.
@@ -191,10 +439,49 @@ pub const SESSION_LOAD: &str = "Session.load";
 pub const SESSION_MESSAGE: &str = "Session.message";
 /// Method to indicate a session is finished.
 pub const SESSION_FINISH: &str = "Session.finish";
+/// Method to abort a session.
+pub const SESSION_ABORT: &str = "Session.abort";
+/// Method to report the parties that caused a session to fail.
+pub const SESSION_BLAME: &str = "Session.blame";
+/// Method to leave a session.
+pub const SESSION_LEAVE: &str = "Session.leave";
+/// Method to get the party signup numbers for a session.
+pub const SESSION_PARTICIPANTS: &str = "Session.participants";
 /// Method to notify of a proposal for signing.
 pub const NOTIFY_PROPOSAL: &str = "Notify.proposal";
 /// Method to notify a proposal has been signed.
 pub const NOTIFY_SIGNED: &str = "Notify.signed";
+/// Method to resume a dropped connection.
+pub const CONNECTION_RESUME: &str = "Connection.resume";
+/// Method to authenticate a connection.
+pub const CONNECTION_AUTHENTICATE: &str = "Connection.authenticate";
+/// Method to check a connection is alive.
+pub const CONNECTION_PING: &str = "Connection.ping";
+/// Method to get information about the server.
+pub const SERVER_INFO: &str = "Server.info";
+
+/// Names of all the service methods.
+pub const METHODS: &[&str] = &[
+    GROUP_CREATE,
+    GROUP_JOIN,
+    GROUP_LEAVE,
+    SESSION_CREATE,
+    SESSION_JOIN,
+    SESSION_SIGNUP,
+    SESSION_LOAD,
+    SESSION_MESSAGE,
+    SESSION_FINISH,
+    SESSION_ABORT,
+    SESSION_BLAME,
+    SESSION_LEAVE,
+    SESSION_PARTICIPANTS,
+    NOTIFY_PROPOSAL,
+    NOTIFY_SIGNED,
+    CONNECTION_RESUME,
+    CONNECTION_AUTHENTICATE,
+    CONNECTION_PING,
+    SERVER_INFO,
+];
 
 /// Notification sent when a session has been created.
 ///
@@ -212,40 +499,485 @@ pub const SESSION_MESSAGE_EVENT: &str = "sessionMessage";
 /// Notification sent when a session has been marked as finished
 /// by all participating clients.
 pub const SESSION_CLOSED_EVENT: &str = "sessionClosed";
+/// Notification sent to the clients in a session when
+/// a participant aborts the session.
+pub const SESSION_ABORTED_EVENT: &str = "sessionAborted";
+/// Notification sent to the clients in a group when a participant
+/// reports the parties that caused a session to fail.
+pub const SESSION_BLAME_EVENT: &str = "sessionBlame";
+/// Notification sent to the remaining clients in a session
+/// when a participant disconnects.
+pub const SESSION_PARTICIPANT_LEFT_EVENT: &str = "sessionParticipantLeft";
+/// Notification sent to the other clients in a group
+/// when a member joins the group.
+pub const GROUP_MEMBER_JOINED_EVENT: &str = "groupMemberJoined";
+/// Notification sent to the remaining clients in a group
+/// when a member leaves the group.
+pub const GROUP_MEMBER_LEFT_EVENT: &str = "groupMemberLeft";
+/// Notification sent to a client with the resume token
+/// for the connection.
+pub const CONNECTION_TOKEN_EVENT: &str = "connectionToken";
+/// Notification sent to the connected clients in a session
+/// when the session has expired and been removed.
+pub const SESSION_EXPIRED_EVENT: &str = "sessionExpired";
+/// Notification sent to a client when it connects with the
+/// challenge used to authenticate the connection.
+pub const CONNECTION_CHALLENGE_EVENT: &str = "connectionChallenge";
 /// Notification sent when a proposal has been received.
 pub const NOTIFY_PROPOSAL_EVENT: &str = "notifyProposal";
 /// Notification sent when a proposal has been signed.
 pub const NOTIFY_SIGNED_EVENT: &str = "notifySigned";
+/// Notification sent to all clients when the server is shutting down.
+pub const SERVER_SHUTDOWN_EVENT: &str = "serverShutdown";
+/// Notification sent to a client when the party number assigned
+/// to it was concurrently assigned to a client on another instance.
+pub const SESSION_SIGNUP_REJECTED_EVENT: &str = "sessionSignupRejected";
+
+/// Names of all the notification events.
+pub const EVENTS: &[&str] = &[
+    SESSION_CREATE_EVENT,
+    SESSION_SIGNUP_EVENT,
+    SESSION_LOAD_EVENT,
+    SESSION_MESSAGE_EVENT,
+    SESSION_CLOSED_EVENT,
+    SESSION_ABORTED_EVENT,
+    SESSION_BLAME_EVENT,
+    SESSION_PARTICIPANT_LEFT_EVENT,
+    GROUP_MEMBER_JOINED_EVENT,
+    GROUP_MEMBER_LEFT_EVENT,
+    CONNECTION_TOKEN_EVENT,
+    SESSION_EXPIRED_EVENT,
+    CONNECTION_CHALLENGE_EVENT,
+    NOTIFY_PROPOSAL_EVENT,
+    NOTIFY_SIGNED_EVENT,
+    SERVER_SHUTDOWN_EVENT,
+    SESSION_SIGNUP_REJECTED_EVENT,
+];
 
-type GroupCreateParams = (String, Parameters);
+/// Information about the server returned by `Server.info`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerInfo {
+    /// Version of the server crate.
+    pub version: String,
+    /// Version of the JSON-RPC protocol.
+    pub protocol: u32,
+    /// Names of the supported methods.
+    pub methods: Vec<String>,
+    /// Names of the supported events.
+    pub events: Vec<String>,
+    /// Limits configured for the server.
+    pub limits: Limits,
+}
+
+#[derive(Deserialize)]
+struct GroupCreateParams(
+    String,
+    Parameters,
+    #[serde(default)] Option<Vec<Address>>,
+);
 type SessionCreateParams = (Uuid, SessionKind, Option<Value>);
 type SessionJoinParams = (Uuid, Uuid, SessionKind);
 type SessionSignupParams = (Uuid, Uuid, SessionKind);
 type SessionLoadParams = (Uuid, Uuid, SessionKind, u16);
-type SessionMessageParams = (Uuid, Uuid, SessionKind, Message);
+type SessionMessageParams = (Uuid, Uuid, SessionKind, SessionMessage);
 type SessionFinishParams = (Uuid, Uuid, u16);
+type SessionAbortParams = (Uuid, Uuid, u16, String, String);
+type SessionBlameParams = (Uuid, Uuid, u16, Blame);
+type SessionLeaveParams = (Uuid, Uuid);
+type SessionParticipantsParams = (Uuid, Uuid);
 type NotifyProposalParams = (Uuid, Uuid, String, String);
 type NotifySignedParams = (Uuid, Uuid, Value);
 
-// Mimics the `Msg` struct
-// from `round-based` but doesn't care
-// about the `body` data.
-#[derive(Serialize, Deserialize)]
-struct Message {
-    round: u16,
-    sender: u16,
-    receiver: Option<u16>,
-    uuid: String,
-    body: serde_json::Value,
+/// Message relayed between the parties in a session.
+///
+/// Mimics the `Msg` struct from `round-based` but
+/// doesn't care about the `body` data.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SessionMessage {
+    /// Round number for the message.
+    pub round: u16,
+    /// Party number of the sender.
+    pub sender: u16,
+    /// Party number of the receiver for peer to peer messages.
+    pub receiver: Option<u16>,
+    /// Identifier for the message.
+    pub uuid: String,
+    /// Opaque message data.
+    pub body: Value,
+}
+
+/// Payload for the `groupMemberJoined` and `groupMemberLeft` events.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupMember {
+    /// Group identifier.
+    pub group_id: Uuid,
+    /// Opaque identifier for the member that joined or left.
+    pub member_id: Uuid,
+    /// Number of members in the group.
+    pub members: usize,
+    /// Number of parties expected by the group.
+    pub parties: u16,
+}
+
+/// Payload for the `sessionSignup` and `sessionLoad` events.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionReady {
+    /// Session identifier.
+    pub session_id: Uuid,
+    /// Sorted party signup numbers.
+    pub participants: Vec<u16>,
+}
+
+/// Payload for the `sessionAborted` event.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionAborted {
+    /// Session identifier.
+    pub session_id: Uuid,
+    /// Reason the session was aborted.
+    #[serde(flatten)]
+    pub abort: Abort,
+}
+
+/// Parties accused of causing a protocol round to fail.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Blame {
+    /// Protocol round that failed.
+    pub round: u16,
+    /// Kind of protocol error.
+    pub kind: String,
+    /// Party numbers of the accused parties.
+    pub culprits: Vec<u16>,
+    /// Message describing the error.
+    #[serde(default)]
+    pub message: String,
+}
+
+/// Payload for the `sessionBlame` event.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionBlame {
+    /// Session identifier.
+    pub session_id: Uuid,
+    /// Party number of the participant that reported the failure.
+    pub party_number: u16,
+    /// Parties accused of causing the failure.
+    #[serde(flatten)]
+    pub blame: Blame,
 }
 
 #[derive(Debug, Serialize)]
-struct Proposal {
+#[serde(rename_all = "camelCase")]
+struct Participants {
+    participants: Vec<u16>,
+    party_number: Option<u16>,
+}
+
+/// Payload for the `notifyProposal` event.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Proposal {
+    /// Session identifier.
     #[serde(rename = "sessionId")]
-    session_id: Uuid,
+    pub session_id: Uuid,
+    /// Identifier for the proposal.
     #[serde(rename = "proposalId")]
-    proposal_id: String,
-    message: String,
+    pub proposal_id: String,
+    /// Message to be signed.
+    pub message: String,
+}
+
+/// Payload for the `sessionSignupRejected` event.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SignupRejected {
+    /// Session identifier.
+    pub session_id: Uuid,
+    /// Party number that was assigned to another participant.
+    pub party_number: u16,
+}
+
+/// Event notification sent to clients.
+///
+/// Serialized as a tuple of the `String` event name
+/// followed by the payload for the event; deserialized
+/// from the same tuple.
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// Session was created.
+    SessionCreate(Session),
+    /// Enough parties have signed up to a session.
+    SessionSignup(SessionReady),
+    /// Enough parties have loaded into a session.
+    SessionLoad(SessionReady),
+    /// Broadcast or peer to peer message.
+    SessionMessage(SessionMessage),
+    /// Session was finished by all participants; the payload
+    /// is the sorted party numbers.
+    SessionClosed(Vec<u16>),
+    /// Session was aborted by a participant.
+    SessionAborted(SessionAborted),
+    /// Participant reported the parties that caused a session to fail.
+    SessionBlame(SessionBlame),
+    /// Party signup was removed from a session; the payload
+    /// is the party number.
+    SessionParticipantLeft(u16),
+    /// Member joined a group.
+    GroupMemberJoined(GroupMember),
+    /// Member left a group.
+    GroupMemberLeft(GroupMember),
+    /// Resume token for the connection.
+    ConnectionToken(Uuid),
+    /// Session expired and was removed; the payload
+    /// is the session identifier.
+    SessionExpired(Uuid),
+    /// Challenge signed to authenticate the connection.
+    ConnectionChallenge(String),
+    /// Proposal for signing.
+    NotifyProposal(Proposal),
+    /// Proposal was signed; the payload is the
+    /// value given by the signer.
+    NotifySigned(Value),
+    /// Server is shutting down; the payload is the number of
+    /// seconds to wait for active sessions to be finished.
+    ServerShutdown(u64),
+    /// Party signup was rejected in favour of a concurrent
+    /// signup on another server instance.
+    SessionSignupRejected(SignupRejected),
+}
+
+impl Event {
+    /// Name of the event.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::SessionCreate(_) => SESSION_CREATE_EVENT,
+            Event::SessionSignup(_) => SESSION_SIGNUP_EVENT,
+            Event::SessionLoad(_) => SESSION_LOAD_EVENT,
+            Event::SessionMessage(_) => SESSION_MESSAGE_EVENT,
+            Event::SessionClosed(_) => SESSION_CLOSED_EVENT,
+            Event::SessionAborted(_) => SESSION_ABORTED_EVENT,
+            Event::SessionBlame(_) => SESSION_BLAME_EVENT,
+            Event::SessionParticipantLeft(_) => SESSION_PARTICIPANT_LEFT_EVENT,
+            Event::GroupMemberJoined(_) => GROUP_MEMBER_JOINED_EVENT,
+            Event::GroupMemberLeft(_) => GROUP_MEMBER_LEFT_EVENT,
+            Event::ConnectionToken(_) => CONNECTION_TOKEN_EVENT,
+            Event::SessionExpired(_) => SESSION_EXPIRED_EVENT,
+            Event::ConnectionChallenge(_) => CONNECTION_CHALLENGE_EVENT,
+            Event::NotifyProposal(_) => NOTIFY_PROPOSAL_EVENT,
+            Event::NotifySigned(_) => NOTIFY_SIGNED_EVENT,
+            Event::ServerShutdown(_) => SERVER_SHUTDOWN_EVENT,
+            Event::SessionSignupRejected(_) => SESSION_SIGNUP_REJECTED_EVENT,
+        }
+    }
+}
+
+impl Serialize for Event {
+    fn serialize<S>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut tuple = serializer.serialize_tuple(2)?;
+        tuple.serialize_element(self.name())?;
+        match self {
+            Event::SessionCreate(payload) => {
+                tuple.serialize_element(payload)?
+            }
+            Event::SessionSignup(payload) | Event::SessionLoad(payload) => {
+                tuple.serialize_element(payload)?
+            }
+            Event::SessionMessage(payload) => {
+                tuple.serialize_element(payload)?
+            }
+            Event::SessionClosed(payload) => {
+                tuple.serialize_element(payload)?
+            }
+            Event::SessionAborted(payload) => {
+                tuple.serialize_element(payload)?
+            }
+            Event::SessionBlame(payload) => tuple.serialize_element(payload)?,
+            Event::SessionParticipantLeft(payload) => {
+                tuple.serialize_element(payload)?
+            }
+            Event::GroupMemberJoined(payload)
+            | Event::GroupMemberLeft(payload) => {
+                tuple.serialize_element(payload)?
+            }
+            Event::ConnectionToken(payload)
+            | Event::SessionExpired(payload) => {
+                tuple.serialize_element(payload)?
+            }
+            Event::ConnectionChallenge(payload) => {
+                tuple.serialize_element(payload)?
+            }
+            Event::NotifyProposal(payload) => {
+                tuple.serialize_element(payload)?
+            }
+            Event::NotifySigned(payload) => tuple.serialize_element(payload)?,
+            Event::ServerShutdown(payload) => {
+                tuple.serialize_element(payload)?
+            }
+            Event::SessionSignupRejected(payload) => {
+                tuple.serialize_element(payload)?
+            }
+        }
+        tuple.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Event {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_tuple(2, EventVisitor)
+    }
+}
+
+/// Visitor for the tuple of the event name and payload.
+struct EventVisitor;
+
+impl<'de> Visitor<'de> for EventVisitor {
+    type Value = Event;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a tuple of the event name and payload")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> std::result::Result<Event, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let name: String = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let event = match name.as_str() {
+            SESSION_CREATE_EVENT => Event::SessionCreate(payload(&mut seq)?),
+            SESSION_SIGNUP_EVENT => Event::SessionSignup(payload(&mut seq)?),
+            SESSION_LOAD_EVENT => Event::SessionLoad(payload(&mut seq)?),
+            SESSION_MESSAGE_EVENT => Event::SessionMessage(payload(&mut seq)?),
+            SESSION_CLOSED_EVENT => Event::SessionClosed(payload(&mut seq)?),
+            SESSION_ABORTED_EVENT => Event::SessionAborted(payload(&mut seq)?),
+            SESSION_BLAME_EVENT => Event::SessionBlame(payload(&mut seq)?),
+            SESSION_PARTICIPANT_LEFT_EVENT => {
+                Event::SessionParticipantLeft(payload(&mut seq)?)
+            }
+            GROUP_MEMBER_JOINED_EVENT => {
+                Event::GroupMemberJoined(payload(&mut seq)?)
+            }
+            GROUP_MEMBER_LEFT_EVENT => {
+                Event::GroupMemberLeft(payload(&mut seq)?)
+            }
+            CONNECTION_TOKEN_EVENT => {
+                Event::ConnectionToken(payload(&mut seq)?)
+            }
+            SESSION_EXPIRED_EVENT => Event::SessionExpired(payload(&mut seq)?),
+            CONNECTION_CHALLENGE_EVENT => {
+                Event::ConnectionChallenge(payload(&mut seq)?)
+            }
+            NOTIFY_PROPOSAL_EVENT => Event::NotifyProposal(payload(&mut seq)?),
+            NOTIFY_SIGNED_EVENT => Event::NotifySigned(payload(&mut seq)?),
+            SERVER_SHUTDOWN_EVENT => Event::ServerShutdown(payload(&mut seq)?),
+            SESSION_SIGNUP_REJECTED_EVENT => {
+                Event::SessionSignupRejected(payload(&mut seq)?)
+            }
+            _ => return Err(de::Error::unknown_variant(&name, EVENTS)),
+        };
+        Ok(event)
+    }
+}
+
+/// Deserialize the payload element of an event tuple.
+fn payload<'de, A, T>(seq: &mut A) -> std::result::Result<T, A::Error>
+where
+    A: SeqAccess<'de>,
+    T: Deserialize<'de>,
+{
+    seq.next_element()?
+        .ok_or_else(|| de::Error::invalid_length(1, &EventVisitor))
+}
+
+impl JsonSchema for Event {
+    fn schema_name() -> String {
+        "Event".to_string()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        let variants = vec![
+            event_schema::<Session>(gen, SESSION_CREATE_EVENT),
+            event_schema::<SessionReady>(gen, SESSION_SIGNUP_EVENT),
+            event_schema::<SessionReady>(gen, SESSION_LOAD_EVENT),
+            event_schema::<SessionMessage>(gen, SESSION_MESSAGE_EVENT),
+            event_schema::<Vec<u16>>(gen, SESSION_CLOSED_EVENT),
+            event_schema::<SessionAborted>(gen, SESSION_ABORTED_EVENT),
+            event_schema::<SessionBlame>(gen, SESSION_BLAME_EVENT),
+            event_schema::<u16>(gen, SESSION_PARTICIPANT_LEFT_EVENT),
+            event_schema::<GroupMember>(gen, GROUP_MEMBER_JOINED_EVENT),
+            event_schema::<GroupMember>(gen, GROUP_MEMBER_LEFT_EVENT),
+            event_schema::<Uuid>(gen, CONNECTION_TOKEN_EVENT),
+            event_schema::<Uuid>(gen, SESSION_EXPIRED_EVENT),
+            event_schema::<String>(gen, CONNECTION_CHALLENGE_EVENT),
+            event_schema::<Proposal>(gen, NOTIFY_PROPOSAL_EVENT),
+            event_schema::<Value>(gen, NOTIFY_SIGNED_EVENT),
+            event_schema::<u64>(gen, SERVER_SHUTDOWN_EVENT),
+            event_schema::<SignupRejected>(gen, SESSION_SIGNUP_REJECTED_EVENT),
+        ];
+        SchemaObject {
+            metadata: Some(Box::new(Metadata {
+                description: Some(
+                    "Event notification sent to clients.".to_string(),
+                ),
+                ..Default::default()
+            })),
+            subschemas: Some(Box::new(SubschemaValidation {
+                one_of: Some(variants),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+/// Schema for an event tuple of the event name and payload.
+fn event_schema<T: JsonSchema>(
+    gen: &mut SchemaGenerator,
+    name: &str,
+) -> Schema {
+    let name_schema = SchemaObject {
+        instance_type: Some(InstanceType::String.into()),
+        const_value: Some(Value::from(name)),
+        ..Default::default()
+    };
+    SchemaObject {
+        metadata: Some(Box::new(Metadata {
+            title: Some(name.to_string()),
+            ..Default::default()
+        })),
+        instance_type: Some(InstanceType::Array.into()),
+        array: Some(Box::new(ArrayValidation {
+            items: Some(SingleOrVec::Vec(vec![
+                name_schema.into(),
+                gen.subschema_for::<T>(),
+            ])),
+            min_items: Some(2),
+            max_items: Some(2),
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+    .into()
+}
+
+impl From<Event> for Response {
+    fn from(event: Event) -> Self {
+        serde_json::to_value(event).unwrap().into()
+    }
 }
 
 /// Service for replying to client requests.
@@ -253,18 +985,41 @@ pub struct ServiceHandler;
 
 #[async_trait]
 impl Service for ServiceHandler {
-    type Data = (usize, Arc<RwLock<State>>, Arc<Mutex<Option<Notification>>>);
+    type Data = (usize, Arc<State>, Arc<Mutex<Vec<Notification>>>);
 
     async fn handle(
         &self,
         req: &Request,
         ctx: &Self::Data,
+    ) -> Result<Option<Response>> {
+        let method = METHODS.iter().find(|m| **m == req.method());
+        let result = self.dispatch(req, ctx).await;
+
+        // Record metrics for known methods
+        if let Some(method) = method {
+            let (_, state, _) = ctx;
+            state.metrics.request(method);
+            if let Err(e) = &result {
+                state.metrics.error(error_name(e));
+            }
+        }
+
+        result
+    }
+}
+
+impl ServiceHandler {
+    /// Dispatch a request to the handler for the method.
+    async fn dispatch(
+        &self,
+        req: &Request,
+        ctx: &<Self as Service>::Data,
     ) -> Result<Option<Response>> {
         let response = match req.method() {
             GROUP_CREATE => {
-                let (conn_id, state, _) = ctx;
+                let (conn_id, state, notification) = ctx;
                 let params: GroupCreateParams = req.deserialize()?;
-                let (label, parameters) = params;
+                let GroupCreateParams(label, parameters, allowlist) = params;
 
                 // If parties is less than two then may as well
                 // use a standard single-party ECDSA private key
@@ -286,56 +1041,168 @@ impl Service for ServiceHandler {
                     )));
                 }
 
-                let group =
+                if state.is_draining() {
+                    return Err(Error::from(Box::from(
+                        ServiceError::ShuttingDown,
+                    )));
+                }
+                let address =
+                    authenticated(conn_id, state, allowlist.is_some())?;
+
+                // Creator automatically joins so must be allowed
+                if let (Some(allowlist), Some(address)) = (&allowlist, address)
+                {
+                    if !allowlist.contains(&address) {
+                        return Err(Error::from(Box::from(
+                            ServiceError::AllowlistCreator(address),
+                        )));
+                    }
+                }
+
+                group_limit(conn_id, state)?;
+                let mut group =
                     Group::new(*conn_id, parameters.clone(), label.clone());
-                let res = serde_json::to_value(&group.uuid).unwrap();
-                let mut writer = state.write().await;
-                writer.groups.insert(group.uuid.clone(), group);
+                group.allowlist = allowlist;
+                let group_id = group.uuid;
+                let res = serde_json::to_value(group_id).unwrap();
+                if let Err(e) = state.save_group(&group) {
+                    state.leave_group(*conn_id);
+                    return Err(Error::from(Box::from(e)));
+                }
+                state.groups.insert(group);
+                state.changes.changed(group_id);
+
+                let token = state.resume_token(*conn_id);
+                notification
+                    .lock()
+                    .await
+                    .push(token_notification(*conn_id, &token));
+
                 Some((req, res).into())
             }
             GROUP_JOIN => {
-                let (conn_id, state, _) = ctx;
+                let (conn_id, state, notification) = ctx;
                 let group_id: Uuid = req.deserialize()?;
-                let mut writer = state.write().await;
-                if let Some(group) = writer.groups.get_mut(&group_id) {
-                    if group.clients.len() == group.params.parties as usize {
-                        let error = ServiceError::GroupFull(group_id);
-                        let err = RpcError::new(
-                            error.to_string(),
-                            Some(CLOSE_CONNECTION.to_string()),
-                        );
-                        Some((req, err).into())
-                    } else {
-                        if let None =
-                            group.clients.iter().find(|c| *c == conn_id)
+                let address = authenticated(conn_id, state, false)?;
+                let mut group = lock_group(&group_id, state).await?;
+                if !group.is_authorized(address.as_ref()) {
+                    return Err(Error::from(Box::from(
+                        ServiceError::Unauthorized(*conn_id, group_id),
+                    )));
+                }
+
+                if group.clients.len() == group.params.parties as usize {
+                    // Returned as a response rather than an error
+                    // so record the error here
+                    let error = ServiceError::GroupFull(group_id);
+                    state.metrics.error(error.name());
+                    let err = RpcError::new(
+                        error.to_string(),
+                        Some(CLOSE_CONNECTION.to_string()),
+                    );
+                    Some((req, err).into())
+                } else {
+                    let mut notification = notification.lock().await;
+                    if !group.clients.iter().any(|c| c == conn_id) {
+                        group_limit(conn_id, state)?;
+                        let member_id = group.add_connection(*conn_id);
+                        state.changes.changed(group_id);
+                        notification.push(member_notification(
+                            Event::GroupMemberJoined,
+                            *conn_id,
+                            member_id,
+                            &group,
+                        ));
+                    }
+                    group.last_activity = Instant::now();
+                    let res = serde_json::to_value(&*group).unwrap();
+
+                    let token = state.resume_token(*conn_id);
+                    notification.push(token_notification(*conn_id, &token));
+
+                    Some((req, res).into())
+                }
+            }
+            GROUP_LEAVE => {
+                let (conn_id, state, notification) = ctx;
+                let group_id: Uuid = req.deserialize()?;
+
+                let mut group =
+                    get_group_mut(conn_id, &group_id, state).await?;
+                let group = &mut *group;
+                let (member, departed) = group.remove_connection(*conn_id);
+                if member.is_some() {
+                    state.leave_group(*conn_id);
+                }
+                tracing::info!(conn_id, %group_id, "group member left");
+
+                if group.clients.is_empty() {
+                    state.remove_group(group);
+                    tracing::info!(%group_id, "removed group");
+                } else {
+                    let mut notification = notification.lock().await;
+                    let mut sessions = Vec::new();
+                    for (session_id, party_number) in departed {
+                        notification.push(participant_left_notification(
+                            group_id,
+                            session_id,
+                            party_number,
+                        ));
+                        if !sessions.contains(&session_id) {
+                            sessions.push(session_id);
+                        }
+                    }
+                    for session_id in sessions {
+                        if let Some(session) =
+                            group.sessions.get_mut(&session_id)
                         {
-                            group.clients.push(*conn_id);
+                            notification.extend(session_closed_notification(
+                                state, group_id, session,
+                            ));
                         }
-                        let res = serde_json::to_value(group).unwrap();
-                        Some((req, res).into())
                     }
-                } else {
-                    return Err(Error::from(Box::from(
-                        ServiceError::GroupDoesNotExist(group_id),
-                    )));
+                    state
+                        .save_group(group)
+                        .map_err(|e| Error::from(Box::from(e)))?;
+                    notification.push(member_notification(
+                        Event::GroupMemberLeft,
+                        *conn_id,
+                        member.unwrap_or_default(),
+                        group,
+                    ));
                 }
+
+                Some(req.into())
             }
             SESSION_CREATE => {
                 let (conn_id, state, notification) = ctx;
                 let params: SessionCreateParams = req.deserialize()?;
                 let (group_id, kind, value) = params;
-                let mut writer = state.write().await;
-                let group =
-                    get_group_mut(&conn_id, &group_id, &mut writer.groups)?;
-                let session = Session::from((kind.clone(), value));
-                let key = session.uuid.clone();
+                if state.is_draining() {
+                    return Err(Error::from(Box::from(
+                        ServiceError::ShuttingDown,
+                    )));
+                }
+                let mut group =
+                    get_group_mut(conn_id, &group_id, state).await?;
+                let group = &mut *group;
+                if let Some(max) = state.options.limits.max_sessions_per_group {
+                    if group.sessions.len() >= max {
+                        return Err(Error::from(Box::from(
+                            ServiceError::TooManySessions(group_id),
+                        )));
+                    }
+                }
+                let session = Session::new(kind.clone(), value, &group.params);
+                let key = session.uuid;
                 group.sessions.insert(key, session.clone());
+                state
+                    .save_group(group)
+                    .map_err(|e| Error::from(Box::from(e)))?;
 
                 if let SessionKind::Keygen = kind {
-                    let value =
-                        serde_json::to_value((SESSION_CREATE_EVENT, &session))
-                            .unwrap();
-                    let response: Response = value.into();
+                    let response: Response =
+                        Event::SessionCreate(session.clone()).into();
 
                     // Notify everyone else in the group a session was created
                     let ctx = Notification::Group {
@@ -343,8 +1210,7 @@ impl Service for ServiceHandler {
                         filter: Some(vec![*conn_id]),
                         response,
                     };
-                    let mut writer = notification.lock().await;
-                    *writer = Some(ctx);
+                    notification.lock().await.push(ctx);
                 }
 
                 let res = serde_json::to_value(&session).unwrap();
@@ -353,13 +1219,14 @@ impl Service for ServiceHandler {
             SESSION_JOIN => {
                 let (conn_id, state, _) = ctx;
                 let params: SessionJoinParams = req.deserialize()?;
-                let (group_id, session_id, _kind) = params;
+                let (group_id, session_id, kind) = params;
 
-                let mut writer = state.write().await;
-                let group =
-                    get_group_mut(&conn_id, &group_id, &mut writer.groups)?;
+                let mut group =
+                    get_group_mut(conn_id, &group_id, state).await?;
+                let group = &mut *group;
                 if let Some(session) = group.sessions.get_mut(&session_id) {
-                    let res = serde_json::to_value(&session).unwrap();
+                    session_kind(session, &kind)?;
+                    let res = serde_json::to_value(session).unwrap();
                     Some((req, res).into())
                 } else {
                     return Err(Error::from(Box::from(
@@ -372,38 +1239,38 @@ impl Service for ServiceHandler {
                 let params: SessionSignupParams = req.deserialize()?;
                 let (group_id, session_id, kind) = params;
 
-                let mut writer = state.write().await;
-                let group =
-                    get_group_mut(&conn_id, &group_id, &mut writer.groups)?;
+                let mut group =
+                    get_group_mut(conn_id, &group_id, state).await?;
+                let group = &mut *group;
                 if let Some(session) = group.sessions.get_mut(&session_id) {
-                    let party_number = session.signup(*conn_id);
+                    session_kind(session, &kind)?;
+                    let num_entries = session.party_signups.len();
+                    let party_number = session
+                        .signup(*conn_id)
+                        .map_err(|e| Error::from(Box::from(e)))?;
 
                     tracing::info!(party_number, "session signup {}", conn_id);
 
-                    // Enough parties are signed up to the session
-                    if threshold(
-                        &kind,
-                        &group.params,
-                        session.party_signups.len(),
-                    ) {
-                        let value = serde_json::to_value((
-                            SESSION_SIGNUP_EVENT,
-                            &session_id,
-                        ))
-                        .unwrap();
-                        let response: Response = value.into();
-                        let ctx = Notification::Session {
-                            group_id,
-                            session_id,
-                            filter: None,
-                            response,
-                        };
-
-                        let mut writer = notification.lock().await;
-                        *writer = Some(ctx);
+                    // Enough parties are signed up to the session,
+                    // repeated signups must not emit the event again
+                    if session.party_signups.len() > num_entries && session.full
+                    {
+                        notification.lock().await.push(
+                            session_ready_notification(
+                                Event::SessionSignup,
+                                group_id,
+                                session,
+                            ),
+                        );
                     }
 
-                    let res = serde_json::to_value(&party_number).unwrap();
+                    let token = state.resume_token(*conn_id);
+                    notification
+                        .lock()
+                        .await
+                        .push(token_notification(*conn_id, &token));
+
+                    let res = serde_json::to_value(party_number).unwrap();
                     Some((req, res).into())
                 } else {
                     return Err(Error::from(Box::from(
@@ -418,33 +1285,23 @@ impl Service for ServiceHandler {
                 let params: SessionLoadParams = req.deserialize()?;
                 let (group_id, session_id, kind, party_number) = params;
 
-                let mut writer = state.write().await;
-                let group =
-                    get_group_mut(&conn_id, &group_id, &mut writer.groups)?;
+                let mut group =
+                    get_group_mut(conn_id, &group_id, state).await?;
+                let group = &mut *group;
                 if let Some(session) = group.sessions.get_mut(&session_id) {
-                    let res = serde_json::to_value(&party_number).unwrap();
+                    session_kind(session, &kind)?;
+                    let res = serde_json::to_value(party_number).unwrap();
                     match session.load(&group.params, *conn_id, party_number) {
                         Ok(_) => {
                             // Enough parties are loaded into the session
-                            if threshold(
-                                &kind,
-                                &group.params,
-                                session.party_signups.len(),
-                            ) {
-                                let value = serde_json::to_value((
-                                    SESSION_LOAD_EVENT,
-                                    &session_id,
-                                ))
-                                .unwrap();
-                                let response: Response = value.into();
-                                let ctx = Notification::Session {
-                                    group_id,
-                                    session_id,
-                                    filter: None,
-                                    response,
-                                };
-                                let mut writer = notification.lock().await;
-                                *writer = Some(ctx);
+                            if session.full {
+                                notification.lock().await.push(
+                                    session_ready_notification(
+                                        Event::SessionLoad,
+                                        group_id,
+                                        session,
+                                    ),
+                                );
                             }
 
                             Some((req, res).into())
@@ -463,10 +1320,13 @@ impl Service for ServiceHandler {
                 let params: SessionFinishParams = req.deserialize()?;
                 let (group_id, session_id, party_number) = params;
 
-                let mut writer = state.write().await;
-                let group =
-                    get_group_mut(&conn_id, &group_id, &mut writer.groups)?;
-                if let Some(session) = group.sessions.get_mut(&session_id) {
+                let mut group =
+                    get_group_mut(conn_id, &group_id, state).await?;
+                let group = &mut *group;
+                let mut session_closed = false;
+                let response = if let Some(session) =
+                    group.sessions.get_mut(&session_id)
+                {
                     let existing_signup = session
                         .party_signups
                         .iter()
@@ -483,37 +1343,11 @@ impl Service for ServiceHandler {
 
                         session.finished.insert(party_number);
 
-                        let mut signups = session
-                            .party_signups
-                            .iter()
-                            .map(|(n, _)| n.clone())
-                            .collect::<Vec<u16>>();
-                        let mut completed = session
-                            .finished
-                            .iter()
-                            .cloned()
-                            .collect::<Vec<u16>>();
-
-                        signups.sort();
-                        completed.sort();
-
-                        if signups == completed {
-                            let value = serde_json::to_value((
-                                SESSION_CLOSED_EVENT,
-                                completed,
-                            ))
-                            .unwrap();
-                            let response: Response = value.into();
-
-                            let ctx = Notification::Session {
-                                group_id,
-                                session_id,
-                                filter: None,
-                                response,
-                            };
-
-                            let mut writer = notification.lock().await;
-                            *writer = Some(ctx);
+                        if let Some(ctx) = session_closed_notification(
+                            state, group_id, session,
+                        ) {
+                            session_closed = true;
+                            notification.lock().await.push(ctx);
                         }
 
                         Some(req.into())
@@ -526,41 +1360,206 @@ impl Service for ServiceHandler {
                     return Err(Error::from(Box::from(
                         ServiceError::SessionDoesNotExist(session_id),
                     )));
+                };
+
+                if session_closed {
+                    state
+                        .save_group(group)
+                        .map_err(|e| Error::from(Box::from(e)))?;
                 }
+
+                response
+            }
+            SESSION_ABORT => {
+                let (conn_id, state, notification) = ctx;
+                let params: SessionAbortParams = req.deserialize()?;
+                let (group_id, session_id, party_number, code, message) =
+                    params;
+
+                let mut group =
+                    get_group_mut(conn_id, &group_id, state).await?;
+                let group = &mut *group;
+                let session =
+                    group.sessions.get_mut(&session_id).ok_or_else(|| {
+                        Error::from(Box::from(
+                            ServiceError::SessionDoesNotExist(session_id),
+                        ))
+                    })?;
+
+                party_owner(session, conn_id, party_number)?;
+
+                if session.aborted.is_some() {
+                    return Err(Error::from(Box::from(
+                        ServiceError::SessionAborted(session_id),
+                    )));
+                }
+
+                tracing::info!(%session_id, party_number, %code, "session aborted");
+                let abort = Abort {
+                    party_number,
+                    code,
+                    message,
+                };
+                let response: Response =
+                    Event::SessionAborted(SessionAborted {
+                        session_id,
+                        abort: abort.clone(),
+                    })
+                    .into();
+                session.abort(abort);
+                state
+                    .save_group(group)
+                    .map_err(|e| Error::from(Box::from(e)))?;
+
+                notification.lock().await.push(Notification::Session {
+                    group_id,
+                    session_id,
+                    filter: None,
+                    response,
+                });
+
+                Some(req.into())
+            }
+            SESSION_BLAME => {
+                let (conn_id, state, notification) = ctx;
+                let params: SessionBlameParams = req.deserialize()?;
+                let (group_id, session_id, party_number, blame) = params;
+
+                let group = get_group(conn_id, &group_id, state).await?;
+                let session = get_session(&group, &session_id)?;
+                party_owner(session, conn_id, party_number)?;
+
+                // Only participants may be accused
+                let participants = session.participants();
+                if let Some(culprit) =
+                    blame.culprits.iter().find(|n| !participants.contains(n))
+                {
+                    return Err(Error::from(Box::from(
+                        ServiceError::PartyDoesNotExist(*culprit),
+                    )));
+                }
+
+                tracing::warn!(
+                    %session_id,
+                    party_number,
+                    culprits = ?blame.culprits,
+                    "session blame");
+
+                let response: Response = Event::SessionBlame(SessionBlame {
+                    session_id,
+                    party_number,
+                    blame,
+                })
+                .into();
+                notification.lock().await.push(Notification::Group {
+                    group_id,
+                    filter: None,
+                    response,
+                });
+
+                Some(req.into())
+            }
+            SESSION_LEAVE => {
+                let (conn_id, state, notification) = ctx;
+                let params: SessionLeaveParams = req.deserialize()?;
+                let (group_id, session_id) = params;
+
+                let mut group =
+                    get_group_mut(conn_id, &group_id, state).await?;
+                let session =
+                    group.sessions.get_mut(&session_id).ok_or_else(|| {
+                        Error::from(Box::from(
+                            ServiceError::SessionDoesNotExist(session_id),
+                        ))
+                    })?;
+
+                let departed = session.remove_connection(*conn_id);
+                if departed.is_empty() {
+                    return Err(Error::from(Box::from(
+                        ServiceError::NotParticipant(*conn_id, session_id),
+                    )));
+                }
+
+                let mut notification = notification.lock().await;
+                for party_number in departed {
+                    tracing::info!(%session_id, party_number, "session participant left");
+                    notification.push(participant_left_notification(
+                        group_id,
+                        session_id,
+                        party_number,
+                    ));
+                }
+                notification.extend(session_closed_notification(
+                    state, group_id, session,
+                ));
+                state
+                    .save_group(&group)
+                    .map_err(|e| Error::from(Box::from(e)))?;
+
+                Some(req.into())
+            }
+            SESSION_PARTICIPANTS => {
+                let (conn_id, state, _) = ctx;
+                let params: SessionParticipantsParams = req.deserialize()?;
+                let (group_id, session_id) = params;
+
+                let group = get_group(conn_id, &group_id, state).await?;
+                let session = get_session(&group, &session_id)?;
+                let participants = Participants {
+                    participants: session.participants(),
+                    party_number: session.party_number(*conn_id),
+                };
+                let res = serde_json::to_value(participants).unwrap();
+                Some((req, res).into())
             }
             SESSION_MESSAGE => {
                 let (conn_id, state, notification) = ctx;
                 let params: SessionMessageParams = req.deserialize()?;
-                let (group_id, session_id, _kind, msg) = params;
-
-                let reader = state.read().await;
+                let (group_id, session_id, kind, msg) = params;
 
                 // Check we have valid group / session
-                let (_group, session) = get_group_session(
-                    &conn_id,
-                    &group_id,
-                    &session_id,
-                    &reader.groups,
-                )?;
+                let group = get_group(conn_id, &group_id, state).await?;
+                let session = get_session(&group, &session_id)?;
+                session_kind(session, &kind)?;
+
+                if session.aborted.is_some() {
+                    return Err(Error::from(Box::from(
+                        ServiceError::SessionAborted(session_id),
+                    )));
+                }
+
+                // Only participants may send messages
+                if !session.party_signups.iter().any(|(_, c)| c == conn_id) {
+                    return Err(Error::from(Box::from(
+                        ServiceError::NotParticipant(*conn_id, session_id),
+                    )));
+                }
+
+                // Sender must be the party number for the caller
+                if !session
+                    .party_signups
+                    .iter()
+                    .any(|(n, c)| c == conn_id && *n == msg.sender)
+                {
+                    return Err(Error::from(Box::from(
+                        ServiceError::BadSender(msg.sender),
+                    )));
+                }
 
                 // Send direct to peer
                 if let Some(receiver) = &msg.receiver {
                     if let Some(s) =
                         session.party_signups.iter().find(|s| s.0 == *receiver)
                     {
-                        let value =
-                            serde_json::to_value((SESSION_MESSAGE_EVENT, msg))
-                                .unwrap();
-
-                        let response: Response = value.into();
+                        let response: Response =
+                            Event::SessionMessage(msg).into();
                         let message = (s.1, response);
 
                         let ctx = Notification::Relay {
                             messages: vec![message],
                         };
 
-                        let mut writer = notification.lock().await;
-                        *writer = Some(ctx);
+                        notification.lock().await.push(ctx);
                     } else {
                         return Err(Error::from(Box::from(
                             ServiceError::BadPeerReceiver(*receiver),
@@ -568,10 +1567,7 @@ impl Service for ServiceHandler {
                     }
                 // Handle broadcast round
                 } else {
-                    let value =
-                        serde_json::to_value((SESSION_MESSAGE_EVENT, msg))
-                            .unwrap();
-                    let response: Response = value.clone().into();
+                    let response: Response = Event::SessionMessage(msg).into();
 
                     let ctx = Notification::Session {
                         group_id,
@@ -580,28 +1576,27 @@ impl Service for ServiceHandler {
                         response,
                     };
 
-                    let mut writer = notification.lock().await;
-                    *writer = Some(ctx);
+                    notification.lock().await.push(ctx);
                 }
 
                 // Must ACK so we indicate the service method exists
                 Some(req.into())
             }
             NOTIFY_PROPOSAL => {
-                let (conn_id, _state, notification) = ctx;
+                let (conn_id, state, notification) = ctx;
                 let params: NotifyProposalParams = req.deserialize()?;
                 let (group_id, session_id, proposal_id, message) = params;
 
+                // Only members of the group may send proposals
+                get_group(conn_id, &group_id, state).await?;
+
                 let proposal = Proposal {
                     session_id,
                     proposal_id,
                     message,
                 };
 
-                let value =
-                    serde_json::to_value((NOTIFY_PROPOSAL_EVENT, &proposal))
-                        .unwrap();
-                let response: Response = value.into();
+                let response: Response = Event::NotifyProposal(proposal).into();
 
                 let ctx = Notification::Group {
                     group_id,
@@ -609,8 +1604,7 @@ impl Service for ServiceHandler {
                     response,
                 };
 
-                let mut writer = notification.lock().await;
-                *writer = Some(ctx);
+                notification.lock().await.push(ctx);
 
                 // Must ACK so we indicate the service method exists
                 Some(req.into())
@@ -621,24 +1615,16 @@ impl Service for ServiceHandler {
                 let params: NotifySignedParams = req.deserialize()?;
                 let (group_id, session_id, value) = params;
 
-                let reader = state.read().await;
-
-                let (_group, session) = get_group_session(
-                    &conn_id,
-                    &group_id,
-                    &session_id,
-                    &reader.groups,
-                )?;
+                let group = get_group(conn_id, &group_id, state).await?;
+                let session = get_session(&group, &session_id)?;
 
                 let participants = session
                     .party_signups
                     .iter()
-                    .map(|(_, c)| c.clone())
+                    .map(|(_, c)| *c)
                     .collect::<Vec<usize>>();
 
-                let value =
-                    serde_json::to_value((NOTIFY_SIGNED_EVENT, value)).unwrap();
-                let response: Response = value.into();
+                let response: Response = Event::NotifySigned(value).into();
 
                 let ctx = Notification::Group {
                     group_id,
@@ -646,89 +1632,297 @@ impl Service for ServiceHandler {
                     response,
                 };
 
-                let mut writer = notification.lock().await;
-                *writer = Some(ctx);
+                notification.lock().await.push(ctx);
 
                 // Must ACK so we indicate the service method exists
                 Some(req.into())
             }
+            CONNECTION_AUTHENTICATE => {
+                let (conn_id, state, _) = ctx;
+                let signature: String = req.deserialize()?;
+
+                let challenge =
+                    state.challenges.lock().unwrap().get(conn_id).cloned();
+                if let Some(challenge) = challenge {
+                    let address = recover_address(&challenge, &signature)
+                        .map_err(|e| Error::from(Box::from(e)))?;
+                    state.challenges.lock().unwrap().remove(conn_id);
+                    state.addresses.lock().unwrap().insert(*conn_id, address);
+
+                    tracing::info!(conn_id, %address, "connection authenticated");
+
+                    let res = serde_json::to_value(address).unwrap();
+                    Some((req, res).into())
+                } else {
+                    return Err(Error::from(Box::from(
+                        ServiceError::NoChallenge(*conn_id),
+                    )));
+                }
+            }
+            CONNECTION_RESUME => {
+                let (conn_id, state, _) = ctx;
+                let token: Uuid = req.deserialize()?;
+
+                match state.resume(*conn_id, &token).await {
+                    Ok(groups) => {
+                        tracing::info!(conn_id, "connection resumed");
+                        let res = serde_json::to_value(&groups).unwrap();
+                        Some((req, res).into())
+                    }
+                    Err(err) => return Err(Error::from(Box::from(err))),
+                }
+            }
+            CONNECTION_PING => {
+                let res = req.params().clone().unwrap_or(Value::Null);
+                Some((req, res).into())
+            }
+            SERVER_INFO => {
+                let (_, state, _) = ctx;
+                let info = ServerInfo {
+                    version: env!("CARGO_PKG_VERSION").to_string(),
+                    protocol: PROTOCOL_VERSION,
+                    methods: METHODS.iter().map(|m| m.to_string()).collect(),
+                    events: EVENTS.iter().map(|e| e.to_string()).collect(),
+                    limits: state.options.limits.clone(),
+                };
+                let res = serde_json::to_value(&info).unwrap();
+                Some((req, res).into())
+            }
             _ => None,
         };
         Ok(response)
     }
 }
 
-fn get_group_mut<'a>(
+/// Name of an error for metrics.
+fn error_name(error: &Error) -> &'static str {
+    match error {
+        Error::Boxed(e) => {
+            if let Some(e) = e.downcast_ref::<ServiceError>() {
+                e.name()
+            } else {
+                "ServerError"
+            }
+        }
+        Error::InvalidParams { .. } => "InvalidParams",
+        Error::InvalidRequest { .. } => "InvalidRequest",
+        Error::MethodNotFound { .. } => "MethodNotFound",
+        Error::Parse { .. } => "Parse",
+    }
+}
+
+/// Verify a connection is authenticated when authentication
+/// is required for the operation.
+fn authenticated(
     conn_id: &usize,
+    state: &State,
+    required: bool,
+) -> Result<Option<Address>> {
+    let address = state.address(conn_id);
+    if address.is_none() && (required || state.options.require_authentication) {
+        return Err(Error::from(Box::from(ServiceError::NotAuthenticated(
+            *conn_id,
+        ))));
+    }
+    Ok(address)
+}
+
+/// Notification sending a resume token to a client.
+fn token_notification(conn_id: usize, token: &Uuid) -> Notification {
+    let response: Response = Event::ConnectionToken(*token).into();
+    Notification::Relay {
+        messages: vec![(conn_id, response)],
+    }
+}
+
+/// Notification sent to the clients in a session when
+/// enough parties have signed up to or loaded into the session.
+fn session_ready_notification(
+    event: fn(SessionReady) -> Event,
+    group_id: Uuid,
+    session: &Session,
+) -> Notification {
+    let ready = SessionReady {
+        session_id: session.uuid,
+        participants: session.participants(),
+    };
+    let response: Response = event(ready).into();
+    Notification::Session {
+        group_id,
+        session_id: session.uuid,
+        filter: None,
+        response,
+    }
+}
+
+/// Notification sent to the other clients in a group
+/// when a member joins or leaves the group.
+pub(crate) fn member_notification(
+    event: fn(GroupMember) -> Event,
+    conn_id: usize,
+    member_id: Uuid,
+    group: &Group,
+) -> Notification {
+    let member = GroupMember {
+        group_id: group.uuid,
+        member_id,
+        members: group.clients.len(),
+        parties: group.params.parties,
+    };
+    let response: Response = event(member).into();
+    Notification::Group {
+        group_id: group.uuid,
+        filter: Some(vec![conn_id]),
+        response,
+    }
+}
+
+/// Close a session when every remaining participant has finished.
+///
+/// Returns the notification sent to the participants
+/// when the session is closed.
+pub(crate) fn session_closed_notification(
+    state: &State,
+    group_id: Uuid,
+    session: &mut Session,
+) -> Option<Notification> {
+    let completed = session.close_if_finished()?;
+    let closed = session.closed.unwrap_or_else(Instant::now);
+    state
+        .metrics
+        .session_finished(session.kind.to_string(), closed - session.created);
+    Some(Notification::Session {
+        group_id,
+        session_id: session.uuid,
+        filter: None,
+        response: Event::SessionClosed(completed).into(),
+    })
+}
+
+/// Notification sent to the remaining participants in a session
+/// when a party signup is removed.
+pub(crate) fn participant_left_notification(
+    group_id: Uuid,
+    session_id: Uuid,
+    party_number: u16,
+) -> Notification {
+    let response: Response = Event::SessionParticipantLeft(party_number).into();
+    Notification::Session {
+        group_id,
+        session_id,
+        filter: None,
+        response,
+    }
+}
+
+/// Acquire the lock for a group.
+async fn lock_group(
     group_id: &Uuid,
-    groups: &'a mut HashMap<Uuid, Group>,
-) -> Result<&'a mut Group> {
-    if let Some(group) = groups.get_mut(group_id) {
-        // Verify connection is part of the group clients
-        if let Some(_) = group.clients.iter().find(|c| *c == conn_id) {
-            Ok(group)
-        } else {
-            return Err(Error::from(Box::from(ServiceError::BadConnection(
-                *conn_id,
-                group_id.clone(),
-            ))));
+    state: &State,
+) -> Result<OwnedMutexGuard<Group>> {
+    if let Some(group) = state.groups.get(group_id) {
+        let group = group.lock_owned().await;
+        // Group may be removed whilst waiting for the lock
+        if !group.removed {
+            return Ok(group);
         }
-    } else {
-        return Err(Error::from(Box::from(ServiceError::GroupDoesNotExist(
-            group_id.clone(),
-        ))));
     }
+    Err(Error::from(Box::from(ServiceError::GroupDoesNotExist(
+        *group_id,
+    ))))
 }
 
-fn get_group<'a>(
+/// Acquire the lock for a group the connection belongs to.
+async fn get_group(
     conn_id: &usize,
     group_id: &Uuid,
-    groups: &'a HashMap<Uuid, Group>,
-) -> Result<&'a Group> {
-    if let Some(group) = groups.get(group_id) {
-        // Verify connection is part of the group clients
-        if let Some(_) = group.clients.iter().find(|c| *c == conn_id) {
-            Ok(group)
-        } else {
-            return Err(Error::from(Box::from(ServiceError::BadConnection(
-                *conn_id,
-                group_id.clone(),
-            ))));
-        }
-    } else {
-        return Err(Error::from(Box::from(ServiceError::GroupDoesNotExist(
-            group_id.clone(),
+    state: &State,
+) -> Result<OwnedMutexGuard<Group>> {
+    let address = state.address(conn_id);
+    let mut group = lock_group(group_id, state).await?;
+    // Verify connection is authorized for the group
+    if !group.is_authorized(address.as_ref()) {
+        return Err(Error::from(Box::from(ServiceError::Unauthorized(
+            *conn_id, *group_id,
         ))));
     }
+    // Verify connection is part of the group clients
+    if group.clients.iter().any(|c| c == conn_id) {
+        group.last_activity = Instant::now();
+        Ok(group)
+    } else {
+        Err(Error::from(Box::from(ServiceError::BadConnection(
+            *conn_id, *group_id,
+        ))))
+    }
 }
 
-fn get_group_session<'a>(
+/// Acquire the lock for a group the connection belongs to
+/// in order to modify the group.
+async fn get_group_mut(
     conn_id: &usize,
     group_id: &Uuid,
-    session_id: &Uuid,
-    groups: &'a HashMap<Uuid, Group>,
-) -> Result<(&'a Group, &'a Session)> {
-    let group = get_group(conn_id, group_id, groups)?;
+    state: &State,
+) -> Result<OwnedMutexGuard<Group>> {
+    let group = get_group(conn_id, group_id, state).await?;
+    state.changes.changed(*group_id);
+    Ok(group)
+}
+
+fn get_session<'a>(group: &'a Group, session_id: &Uuid) -> Result<&'a Session> {
     if let Some(session) = group.sessions.get(session_id) {
-        Ok((group, session))
+        Ok(session)
     } else {
-        return Err(Error::from(Box::from(ServiceError::SessionDoesNotExist(
-            session_id.clone(),
-        ))));
+        Err(Error::from(Box::from(ServiceError::SessionDoesNotExist(
+            *session_id,
+        ))))
     }
 }
 
-/// Helper to determine if we met a session party threshold.
-fn threshold(
-    kind: &SessionKind,
-    params: &Parameters,
-    num_entries: usize,
-) -> bool {
-    let parties = params.parties as usize;
-    let threshold = params.threshold as usize;
-    let required_num_entries = match kind {
-        SessionKind::Keygen => parties,
-        SessionKind::Sign => threshold + 1,
-    };
-    num_entries == required_num_entries
+/// Helper to verify a connection may belong to another group.
+///
+/// Counts the group against the limit for the connection so callers
+/// must skip this check when the connection is already a member of
+/// the group being joined.
+fn group_limit(conn_id: &usize, state: &State) -> Result<()> {
+    if state.join_group(*conn_id) {
+        Ok(())
+    } else {
+        Err(Error::from(Box::from(ServiceError::TooManyGroups(
+            *conn_id,
+        ))))
+    }
+}
+
+/// Helper to verify a party number in a session belongs to the caller.
+fn party_owner(
+    session: &Session,
+    conn_id: &usize,
+    party_number: u16,
+) -> Result<()> {
+    match session
+        .party_signups
+        .iter()
+        .find(|(n, _)| *n == party_number)
+    {
+        Some((_, conn)) if conn == conn_id => Ok(()),
+        Some(_) => {
+            Err(Error::from(Box::from(ServiceError::BadParty(party_number))))
+        }
+        None => Err(Error::from(Box::from(ServiceError::PartyDoesNotExist(
+            party_number,
+        )))),
+    }
+}
+
+/// Helper to verify the kind requested by a client matches the session.
+fn session_kind(session: &Session, kind: &SessionKind) -> Result<()> {
+    if &session.kind != kind {
+        return Err(Error::from(Box::from(ServiceError::KindMismatch(
+            session.uuid,
+            session.kind.clone(),
+            kind.clone(),
+        ))));
+    }
+    Ok(())
 }
@@ -0,0 +1,204 @@
+//! Storage for group and session metadata.
+//!
+//! Groups and sessions are always routed from memory; a storage
+//! implementation keeps a copy of the metadata so that groups
+//! and sessions survive a server restart.
+//!
+//! Only the information required to restore groups and sessions
+//! is stored; connections, party signups and message bodies
+//! are never written to storage.
+//!
+//! After a restart clients join the group again and use
+//! `Session.load` to re-assign their party signup numbers.
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::auth::Address;
+use crate::{Abort, Group, Parameters, Result, Session, SessionKind};
+
+/// Stored metadata for a group.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupRecord {
+    /// Unique identifier for the group.
+    pub uuid: Uuid,
+    /// Parameters for key generation.
+    pub params: Parameters,
+    /// Human-readable label for the group.
+    pub label: String,
+    /// Addresses allowed to join the group.
+    pub allowlist: Option<Vec<Address>>,
+    /// Sessions belonging to the group.
+    pub sessions: Vec<SessionRecord>,
+}
+
+/// Stored metadata for a session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionRecord {
+    /// Unique identifier for the session.
+    pub uuid: Uuid,
+    /// Kind of the session.
+    pub kind: SessionKind,
+    /// Public value associated with the session.
+    pub value: Option<Value>,
+    /// Whether all the participants finished the session.
+    pub finished: bool,
+    /// Reason the session was aborted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aborted: Option<Abort>,
+}
+
+impl From<&Group> for GroupRecord {
+    fn from(group: &Group) -> Self {
+        Self {
+            uuid: group.uuid,
+            params: group.params.clone(),
+            label: group.label.clone(),
+            allowlist: group.allowlist.clone(),
+            sessions: group
+                .sessions
+                .values()
+                .map(|session| SessionRecord {
+                    uuid: session.uuid,
+                    kind: session.kind.clone(),
+                    value: session.value.clone(),
+                    finished: session.closed.is_some(),
+                    aborted: session.aborted.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<GroupRecord> for Group {
+    fn from(record: GroupRecord) -> Self {
+        let params = &record.params;
+        let sessions = record
+            .sessions
+            .into_iter()
+            .map(|session_record| {
+                let mut session = Session::new(
+                    session_record.kind,
+                    session_record.value,
+                    params,
+                );
+                session.uuid = session_record.uuid;
+                if session_record.finished {
+                    session.closed = Some(Instant::now());
+                }
+                if let Some(abort) = session_record.aborted {
+                    session.abort(abort);
+                }
+                (session.uuid, session)
+            })
+            .collect();
+        Self {
+            uuid: record.uuid,
+            params: record.params,
+            label: record.label,
+            allowlist: record.allowlist,
+            sessions,
+            ..Default::default()
+        }
+    }
+}
+
+/// Storage for group and session metadata.
+pub trait Storage: fmt::Debug + Send + Sync {
+    /// Load all the stored groups.
+    fn load(&self) -> Result<Vec<GroupRecord>>;
+
+    /// Save a group replacing any existing record.
+    fn save(&self, group: GroupRecord) -> Result<()>;
+
+    /// Remove a group.
+    fn remove(&self, group_id: &Uuid) -> Result<()>;
+}
+
+/// Storage that keeps records in memory.
+///
+/// Records do not survive a restart of the process but
+/// are retained when a server is re-created using the
+/// same storage.
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    groups: Mutex<HashMap<Uuid, GroupRecord>>,
+}
+
+impl Storage for MemoryStorage {
+    fn load(&self) -> Result<Vec<GroupRecord>> {
+        Ok(self.groups.lock().unwrap().values().cloned().collect())
+    }
+
+    fn save(&self, group: GroupRecord) -> Result<()> {
+        self.groups.lock().unwrap().insert(group.uuid, group);
+        Ok(())
+    }
+
+    fn remove(&self, group_id: &Uuid) -> Result<()> {
+        self.groups.lock().unwrap().remove(group_id);
+        Ok(())
+    }
+}
+
+/// Storage that writes a JSON file for each group to a directory.
+#[derive(Debug)]
+pub struct FileStorage {
+    dir: PathBuf,
+}
+
+impl FileStorage {
+    /// Create file storage in `dir`.
+    ///
+    /// The directory is created if it does not exist.
+    pub fn new(dir: PathBuf) -> Result<Self> {
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    fn path(&self, group_id: &Uuid) -> PathBuf {
+        self.dir.join(format!("{}.json", group_id))
+    }
+}
+
+impl Storage for FileStorage {
+    fn load(&self) -> Result<Vec<GroupRecord>> {
+        let mut groups = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let contents = fs::read(&path)?;
+            groups.push(serde_json::from_slice(&contents)?);
+        }
+        Ok(groups)
+    }
+
+    fn save(&self, group: GroupRecord) -> Result<()> {
+        // Write to a temporary file and rename so that
+        // a crash never leaves a partial record
+        let path = self.path(&group.uuid);
+        let temp = path.with_extension("json.tmp");
+        fs::write(&temp, serde_json::to_vec(&group)?)?;
+        fs::rename(&temp, &path)?;
+        Ok(())
+    }
+
+    fn remove(&self, group_id: &Uuid) -> Result<()> {
+        match fs::remove_file(self.path(group_id)) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use k256::ecdsa::SigningKey;
+use mpc_websocket::{
+    auth::{recover_address, Address, AuthError},
+    services::*,
+    Server, ServerOptions,
+};
+use serde_json::json;
+use sha3::{Digest, Keccak256};
+use uuid::Uuid;
+use warp::test::WsClient;
+
+mod common;
+use common::*;
+
+/// Signature of "Some data" from the web3.js documentation.
+const SIGNATURE: &str = "0xb91467e570a6466aa9e9876cbcd013baba02900b8979d43fe208a4a4f339f5fd6007e74cd82e037b800186422fc2da167c747ef045e5d18a5f5d4300f8e1a0291c";
+
+/// Address for the private key used by the web3.js documentation.
+const ADDRESS: &str = "0x2c7536E3605D9C16a7a3D7b1898e529396a65c23";
+
+/// Private key used by the web3.js documentation.
+const PRIVATE_KEY: &str =
+    "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+
+/// Order of the secp256k1 curve.
+const CURVE_ORDER: [u8; 32] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xfe, 0xba, 0xae, 0xdc, 0xe6, 0xaf, 0x48, 0xa0, 0x3b,
+    0xbf, 0xd2, 0x5e, 0x8c, 0xd0, 0x36, 0x41, 0x41,
+];
+
+fn signature_bytes() -> Vec<u8> {
+    hex::decode(SIGNATURE.strip_prefix("0x").unwrap()).unwrap()
+}
+
+/// Sign a message using `personal_sign`.
+fn personal_sign(key: &SigningKey, message: &str) -> String {
+    let digest = Keccak256::new_with_prefix(format!(
+        "\x19Ethereum Signed Message:\n{}{}",
+        message.len(),
+        message
+    ));
+    let (signature, recovery_id) = key.sign_digest_recoverable(digest).unwrap();
+    let mut bytes = signature.to_bytes().to_vec();
+    bytes.push(recovery_id.to_byte() + 27);
+    format!("0x{}", hex::encode(bytes))
+}
+
+/// Address for a signing key.
+fn address(key: &SigningKey) -> Address {
+    recover_address("address", &personal_sign(key, "address")).unwrap()
+}
+
+/// Authenticate a client by signing the challenge sent on connection.
+async fn authenticate(client: &mut WsClient, key: &SigningKey) -> Address {
+    let challenge = event(client, CONNECTION_CHALLENGE_EVENT).await;
+    let challenge = challenge.as_str().unwrap();
+    let signature = personal_sign(key, challenge);
+    let address = call(client, CONNECTION_AUTHENTICATE, json!(signature)).await;
+    serde_json::from_value(address).unwrap()
+}
+
+#[test]
+fn recover_known_vector() {
+    let expected: Address = ADDRESS.parse().unwrap();
+    assert_eq!(expected, recover_address("Some data", SIGNATURE).unwrap());
+
+    // Prefix is optional
+    let unprefixed = SIGNATURE.strip_prefix("0x").unwrap();
+    assert_eq!(expected, recover_address("Some data", unprefixed).unwrap());
+
+    // Recovery identifier may be 0/1 or 27/28
+    let mut bytes = signature_bytes();
+    bytes[64] -= 27;
+    let signature = hex::encode(&bytes);
+    assert_eq!(expected, recover_address("Some data", &signature).unwrap());
+
+    // Signed by the documented private key
+    let key =
+        SigningKey::from_slice(&hex::decode(PRIVATE_KEY).unwrap()).unwrap();
+    assert_eq!(expected, address(&key));
+}
+
+#[test]
+fn recover_high_s() {
+    // Replace `s` with `n - s` and flip the recovery identifier
+    let mut bytes = signature_bytes();
+    let mut borrow = 0i16;
+    for i in (0..32).rev() {
+        let value = CURVE_ORDER[i] as i16 - bytes[32 + i] as i16 - borrow;
+        borrow = if value < 0 { 1 } else { 0 };
+        bytes[32 + i] = (value + 256 * borrow) as u8;
+    }
+    bytes[64] = if bytes[64] == 27 { 28 } else { 27 };
+
+    let expected: Address = ADDRESS.parse().unwrap();
+    let signature = hex::encode(&bytes);
+    assert_eq!(expected, recover_address("Some data", &signature).unwrap());
+}
+
+#[test]
+fn recover_invalid() {
+    let mut bytes = signature_bytes();
+    bytes.pop();
+    assert!(matches!(
+        recover_address("Some data", &hex::encode(&bytes)),
+        Err(AuthError::InvalidSignature)
+    ));
+
+    assert!(matches!(
+        recover_address("Some data", "0xnothex"),
+        Err(AuthError::InvalidSignature)
+    ));
+
+    let mut bytes = signature_bytes();
+    bytes[64] = 29;
+    assert!(matches!(
+        recover_address("Some data", &hex::encode(&bytes)),
+        Err(AuthError::InvalidSignature)
+    ));
+
+    // Different message recovers a different address
+    let expected: Address = ADDRESS.parse().unwrap();
+    assert_ne!(expected, recover_address("Other data", SIGNATURE).unwrap());
+}
+
+#[tokio::test]
+async fn require_authentication() {
+    let options = ServerOptions::default()
+        .tracing(false)
+        .require_authentication(true);
+    let server = Server::new(options).unwrap();
+    let key = SigningKey::from_slice(&[7u8; 32]).unwrap();
+
+    let mut client = connect(&server).await;
+    let challenge = event(&mut client, CONNECTION_CHALLENGE_EVENT).await;
+    let challenge = challenge.as_str().unwrap();
+    assert!(challenge.starts_with("Sign in to 127.0.0.1:"));
+    assert!(challenge.contains("\n\nNonce: "));
+
+    let params = json!(["test", {"parties": 2, "threshold": 1}]);
+    let response = request(&mut client, GROUP_CREATE, params.clone()).await;
+    assert!(error(&response).ends_with("is not authenticated"));
+
+    let signature = personal_sign(&key, challenge);
+    let result =
+        call(&mut client, CONNECTION_AUTHENTICATE, json!(signature)).await;
+    assert_eq!(json!(address(&key)), result);
+    let response = request(&mut client, GROUP_CREATE, params).await;
+    assert!(response.get("error").is_none());
+}
+
+#[tokio::test]
+async fn group_allowlist() {
+    let options = ServerOptions::default().tracing(false);
+    let server = Server::new(options).unwrap();
+    let alice_key = SigningKey::from_slice(&[1u8; 32]).unwrap();
+    let bob_key = SigningKey::from_slice(&[2u8; 32]).unwrap();
+    let mallory_key = SigningKey::from_slice(&[3u8; 32]).unwrap();
+
+    let mut alice = connect(&server).await;
+    let alice_address = authenticate(&mut alice, &alice_key).await;
+    let bob_address = address(&bob_key);
+
+    // Creator must be included in the allowlist
+    let response = request(
+        &mut alice,
+        GROUP_CREATE,
+        json!(["test", {"parties": 2, "threshold": 1}, [bob_address]]),
+    )
+    .await;
+    assert_eq!(
+        format!("group allowlist must include the address {}", alice_address),
+        error(&response)
+    );
+
+    let group_id = call(
+        &mut alice,
+        GROUP_CREATE,
+        json!([
+            "test",
+            {"parties": 2, "threshold": 1},
+            [alice_address, bob_address],
+        ]),
+    )
+    .await;
+
+    // Unauthenticated and unlisted clients are rejected
+    let mut carol = connect(&server).await;
+    let response = request(&mut carol, GROUP_JOIN, json!(group_id)).await;
+    assert!(error(&response).contains("is not authorized for the group"));
+
+    let mut mallory = connect(&server).await;
+    authenticate(&mut mallory, &mallory_key).await;
+    let response = request(&mut mallory, GROUP_JOIN, json!(group_id)).await;
+    assert!(error(&response).contains("is not authorized for the group"));
+
+    let mut bob = connect(&server).await;
+    assert_eq!(bob_address, authenticate(&mut bob, &bob_key).await);
+    let response = request(&mut bob, GROUP_JOIN, json!(group_id)).await;
+    assert!(response.get("error").is_none());
+}
+
+#[tokio::test]
+async fn notify_proposal_membership() {
+    let options = ServerOptions::default().tracing(false);
+    let server = Server::new(options).unwrap();
+    let alice_key = SigningKey::from_slice(&[1u8; 32]).unwrap();
+    let bob_key = SigningKey::from_slice(&[2u8; 32]).unwrap();
+    let mallory_key = SigningKey::from_slice(&[3u8; 32]).unwrap();
+
+    let mut alice = connect(&server).await;
+    let alice_address = authenticate(&mut alice, &alice_key).await;
+    let mut bob = connect(&server).await;
+    let bob_address = authenticate(&mut bob, &bob_key).await;
+    let group_id = call(
+        &mut alice,
+        GROUP_CREATE,
+        json!([
+            "test",
+            {"parties": 2, "threshold": 1},
+            [alice_address, bob_address],
+        ]),
+    )
+    .await;
+    let session_id = Uuid::new_v4();
+    let proposal =
+        |message: &str| json!([group_id, session_id, "proposal", message]);
+
+    // Unauthenticated, unlisted and non-member clients are rejected
+    let mut carol = connect(&server).await;
+    let response =
+        request(&mut carol, NOTIFY_PROPOSAL, proposal("carol")).await;
+    assert!(error(&response).contains("is not authorized for the group"));
+
+    let mut mallory = connect(&server).await;
+    authenticate(&mut mallory, &mallory_key).await;
+    let response =
+        request(&mut mallory, NOTIFY_PROPOSAL, proposal("mallory")).await;
+    assert!(error(&response).contains("is not authorized for the group"));
+
+    let response = request(&mut bob, NOTIFY_PROPOSAL, proposal("bob")).await;
+    assert!(error(&response).contains("does not belong to the group"));
+
+    // Members may send proposals
+    call(&mut bob, GROUP_JOIN, json!(group_id)).await;
+    let response = request(&mut bob, NOTIFY_PROPOSAL, proposal("bob")).await;
+    assert!(response.get("error").is_none());
+    let payload = event(&mut alice, NOTIFY_PROPOSAL_EVENT).await;
+    assert_eq!("bob", payload["message"]);
+}
@@ -0,0 +1,198 @@
+//! Helpers shared by the integration tests.
+#![allow(dead_code)]
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use json_rpc2::{futures::Service, Error, Request, Response};
+use mpc_websocket::{services::*, Notification, Server, State};
+use serde_json::{json, Value};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+use warp::test::WsClient;
+
+/// Call a service method as the client `conn_id`.
+pub async fn handle(
+    state: &Arc<State>,
+    conn_id: usize,
+    method: &str,
+    params: Value,
+) -> (json_rpc2::Result<Option<Response>>, Vec<Notification>) {
+    let notification = Arc::new(Mutex::new(Vec::new()));
+    let request =
+        Request::new(Some(json!(1)), method.to_string(), Some(params));
+    let ctx = (conn_id, Arc::clone(state), Arc::clone(&notification));
+    let result = ServiceHandler.handle(&request, &ctx).await;
+    let notifications = std::mem::take(&mut *notification.lock().await);
+    (result, notifications)
+}
+
+/// Get the result value from a service response.
+pub fn result(response: json_rpc2::Result<Option<Response>>) -> Value {
+    let response = response.unwrap().unwrap();
+    response.result().clone().unwrap()
+}
+
+/// Get the service error from a failed service call.
+pub fn service_error(
+    response: json_rpc2::Result<Option<Response>>,
+) -> ServiceError {
+    match response {
+        Err(Error::Boxed(e)) => *e.downcast::<ServiceError>().unwrap(),
+        _ => panic!("expected service error"),
+    }
+}
+
+/// Get the event name and payload sent by a notification.
+pub fn notification_event(notification: &Notification) -> Value {
+    match notification {
+        Notification::Group { response, .. }
+        | Notification::Session { response, .. } => {
+            response.result().clone().unwrap()
+        }
+        _ => panic!("expected group or session notification"),
+    }
+}
+
+/// Connect a websocket client to the server.
+pub async fn connect(server: &Server) -> WsClient {
+    connect_path(server, "/mpc").await
+}
+
+/// Connect a websocket client to the server using a request path.
+pub async fn connect_path(server: &Server, path: &str) -> WsClient {
+    warp::test::ws()
+        .path(path)
+        .handshake(server.routes())
+        .await
+        .unwrap()
+}
+
+/// Receive the next message sent to a websocket client.
+pub async fn recv(client: &mut WsClient) -> Value {
+    let message = tokio::time::timeout(Duration::from_secs(5), client.recv())
+        .await
+        .expect("timed out waiting for message")
+        .unwrap();
+    serde_json::from_str(message.to_str().unwrap()).unwrap()
+}
+
+/// Wait for an event ignoring other messages.
+pub async fn event(client: &mut WsClient, name: &str) -> Value {
+    loop {
+        let message = recv(client).await;
+        if message["result"][0] == name {
+            return message["result"][1].clone();
+        }
+    }
+}
+
+/// Call a method and wait for the response ignoring other messages.
+pub async fn request(
+    client: &mut WsClient,
+    method: &str,
+    params: Value,
+) -> Value {
+    let id = Uuid::new_v4().to_string();
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "method": method,
+        "params": params,
+    });
+    client.send_text(request.to_string()).await;
+    loop {
+        let message = recv(client).await;
+        if message["id"] == id {
+            return message;
+        }
+    }
+}
+
+/// Call a method and wait for the result ignoring other messages.
+pub async fn call(client: &mut WsClient, method: &str, params: Value) -> Value {
+    request(client, method, params).await["result"].clone()
+}
+
+/// Get the error message from a response.
+pub fn error(response: &Value) -> &str {
+    response["error"]["message"].as_str().unwrap()
+}
+
+/// Create a group of `parties` members with connection identifiers
+/// starting at one and a session of `kind` that the first `signups`
+/// members have signed up to in order.
+pub async fn setup(
+    parties: u16,
+    kind: &str,
+    signups: usize,
+) -> (Arc<State>, Uuid, Uuid) {
+    let state = Arc::new(State::default());
+    let (response, _) = handle(
+        &state,
+        1,
+        GROUP_CREATE,
+        json!(["test", {"parties": parties, "threshold": 1}]),
+    )
+    .await;
+    let group_id: Uuid = serde_json::from_value(result(response)).unwrap();
+    for conn_id in 2..=parties as usize {
+        let (response, _) =
+            handle(&state, conn_id, GROUP_JOIN, json!(group_id)).await;
+        result(response);
+    }
+
+    let (response, _) =
+        handle(&state, 1, SESSION_CREATE, json!([group_id, kind, null])).await;
+    let session_id: Uuid =
+        serde_json::from_value(result(response)["uuid"].clone()).unwrap();
+    for conn_id in 1..=signups {
+        let (response, _) = handle(
+            &state,
+            conn_id,
+            SESSION_SIGNUP,
+            json!([group_id, session_id, kind]),
+        )
+        .await;
+        assert_eq!(json!(conn_id), result(response));
+    }
+
+    (state, group_id, session_id)
+}
+
+/// Create a group that all the websocket clients have joined
+/// where the number of parties is the number of clients.
+pub async fn setup_group(clients: &mut [&mut WsClient]) -> Value {
+    let parties = clients.len();
+    let group_id = call(
+        clients[0],
+        GROUP_CREATE,
+        json!(["test", {"parties": parties, "threshold": 1}]),
+    )
+    .await;
+    for client in clients.iter_mut().skip(1) {
+        call(client, GROUP_JOIN, json!(group_id)).await;
+    }
+    group_id
+}
+
+/// Create a group that all the websocket clients have joined
+/// and a session of `kind`; when `signup` is set all the clients
+/// sign up to the session in order.
+pub async fn setup_clients(
+    clients: &mut [&mut WsClient],
+    kind: &str,
+    signup: bool,
+) -> (Value, Value) {
+    let group_id = setup_group(clients).await;
+    let session =
+        call(clients[0], SESSION_CREATE, json!([group_id, kind, null])).await;
+    let session_id = session["uuid"].clone();
+    if signup {
+        for client in clients.iter_mut() {
+            let params = json!([group_id, session_id, kind]);
+            call(client, SESSION_SIGNUP, params).await;
+        }
+    }
+    (group_id, session_id)
+}
@@ -0,0 +1,69 @@
+use std::time::Duration;
+
+use mpc_websocket::{services::*, Server, ServerOptions};
+use serde_json::json;
+
+mod common;
+use common::*;
+
+fn server() -> Server {
+    let options = ServerOptions::default()
+        .tracing(false)
+        .resume_grace_period(Duration::ZERO);
+    Server::new(options).unwrap()
+}
+
+#[tokio::test]
+async fn disconnect_frees_slot() {
+    let server = server();
+    let mut alice = connect(&server).await;
+    let mut bob = connect(&server).await;
+    let mut carol = connect(&server).await;
+    let (group_id, session_id) =
+        setup_clients(&mut [&mut alice, &mut bob, &mut carol], "keygen", false)
+            .await;
+
+    let params = json!([group_id, session_id, "keygen"]);
+    assert_eq!(
+        json!(1),
+        call(&mut alice, SESSION_SIGNUP, params.clone()).await
+    );
+    assert_eq!(
+        json!(2),
+        call(&mut bob, SESSION_SIGNUP, params.clone()).await
+    );
+
+    drop(bob);
+    assert_eq!(
+        json!(2),
+        event(&mut alice, SESSION_PARTICIPANT_LEFT_EVENT).await
+    );
+
+    // Slot of the departed participant is re-used
+    assert_eq!(json!(2), call(&mut carol, SESSION_SIGNUP, params).await);
+    let participants = call(
+        &mut alice,
+        SESSION_PARTICIPANTS,
+        json!([group_id, session_id]),
+    )
+    .await;
+    assert_eq!(json!([1, 2]), participants["participants"]);
+}
+
+#[tokio::test]
+async fn disconnect_closes_finished_session() {
+    let server = server();
+    let mut alice = connect(&server).await;
+    let mut bob = connect(&server).await;
+    let (group_id, session_id) =
+        setup_clients(&mut [&mut alice, &mut bob], "keygen", false).await;
+
+    let params = json!([group_id, session_id, "keygen"]);
+    call(&mut alice, SESSION_SIGNUP, params.clone()).await;
+    call(&mut bob, SESSION_SIGNUP, params).await;
+    call(&mut alice, SESSION_FINISH, json!([group_id, session_id, 1])).await;
+
+    // Every remaining participant has finished
+    drop(bob);
+    assert_eq!(json!([1]), event(&mut alice, SESSION_CLOSED_EVENT).await);
+}
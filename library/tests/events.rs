@@ -0,0 +1,126 @@
+use mpc_websocket::{services::*, Abort, Session};
+use schemars::schema_for;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+/// Check a committed schema file matches the generated schema.
+fn assert_schema(name: &str, schema: Value) {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("schema")
+        .join(name);
+    let contents = std::fs::read_to_string(path).unwrap();
+    let committed: Value = serde_json::from_str(&contents).unwrap();
+    assert_eq!(
+        committed, schema,
+        "schema/{} is out of date, run `cargo run --example schema`",
+        name
+    );
+}
+
+#[test]
+fn event_schema() {
+    let schema = serde_json::to_value(schema_for!(Event)).unwrap();
+    let titles = schema["oneOf"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|variant| variant["title"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(EVENTS, titles.as_slice());
+
+    assert_schema("event.json", schema);
+    assert_schema(
+        "server-info.json",
+        serde_json::to_value(schema_for!(ServerInfo)).unwrap(),
+    );
+}
+
+#[test]
+fn event_tuple() {
+    let session_id = Uuid::new_v4();
+    let event = Event::SessionSignup(SessionReady {
+        session_id,
+        participants: vec![1, 2],
+    });
+    assert_eq!(SESSION_SIGNUP_EVENT, event.name());
+    assert_eq!(
+        json!([
+            SESSION_SIGNUP_EVENT,
+            {"sessionId": session_id, "participants": [1, 2]},
+        ]),
+        serde_json::to_value(&event).unwrap()
+    );
+
+    let event = Event::SessionParticipantLeft(2);
+    assert_eq!(
+        json!([SESSION_PARTICIPANT_LEFT_EVENT, 2]),
+        serde_json::to_value(&event).unwrap()
+    );
+}
+
+#[test]
+fn event_round_trip() {
+    let session_id = Uuid::new_v4();
+    let events = vec![
+        Event::SessionCreate(Session::default()),
+        Event::SessionSignup(SessionReady {
+            session_id,
+            participants: vec![1, 2],
+        }),
+        Event::SessionMessage(SessionMessage {
+            round: 1,
+            sender: 1,
+            receiver: Some(2),
+            uuid: session_id.to_string(),
+            body: json!({"data": [1, 2, 3]}),
+        }),
+        Event::SessionClosed(vec![1, 2]),
+        Event::SessionAborted(SessionAborted {
+            session_id,
+            abort: Abort {
+                party_number: 1,
+                code: "timeout".to_string(),
+                message: "timed out".to_string(),
+            },
+        }),
+        Event::SessionBlame(SessionBlame {
+            session_id,
+            party_number: 1,
+            blame: Blame {
+                round: 2,
+                kind: "keygen".to_string(),
+                culprits: vec![3],
+                message: "invalid share".to_string(),
+            },
+        }),
+        Event::SessionParticipantLeft(2),
+        Event::GroupMemberLeft(GroupMember {
+            group_id: Uuid::new_v4(),
+            member_id: Uuid::new_v4(),
+            members: 1,
+            parties: 3,
+        }),
+        Event::ConnectionToken(Uuid::new_v4()),
+        Event::ConnectionChallenge("nonce".to_string()),
+        Event::NotifyProposal(Proposal {
+            session_id,
+            proposal_id: "1".to_string(),
+            message: "hello".to_string(),
+        }),
+        Event::NotifySigned(json!({"signature": "0x00"})),
+        Event::ServerShutdown(60),
+    ];
+
+    for event in events {
+        let value = serde_json::to_value(&event).unwrap();
+        let decoded: Event = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(event.name(), decoded.name());
+        assert_eq!(value, serde_json::to_value(&decoded).unwrap());
+    }
+
+    let unknown = serde_json::from_value::<Event>(json!(["unknown", null]));
+    assert!(unknown.is_err());
+    let missing =
+        serde_json::from_value::<Event>(json!([SESSION_CLOSED_EVENT]));
+    assert!(missing.is_err());
+}
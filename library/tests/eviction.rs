@@ -0,0 +1,79 @@
+use std::time::Duration;
+
+use mpc_websocket::{services::*, Limits, Server, ServerOptions};
+use serde_json::json;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// Encode a masked websocket text frame using a zero mask.
+fn frame(text: &str) -> Vec<u8> {
+    let mut frame = vec![0x81, 0x80 | 127];
+    frame.extend_from_slice(&(text.len() as u64).to_be_bytes());
+    frame.extend_from_slice(&[0, 0, 0, 0]);
+    frame.extend_from_slice(text.as_bytes());
+    frame
+}
+
+#[tokio::test]
+async fn evict_slow_consumer() {
+    let limits = Limits {
+        send_queue_capacity: 1,
+        ..Default::default()
+    };
+    let options = ServerOptions::default()
+        .tracing(false)
+        .metrics(true)
+        .limits(limits);
+    let server = Server::new(options).unwrap();
+    let state = server.state();
+    let (addr, serve) =
+        warp::serve(server.routes()).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(serve);
+
+    // Client that sends requests but never reads the replies
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let handshake = format!(
+        "GET /mpc HTTP/1.1\r\n\
+        Host: {}\r\n\
+        Connection: Upgrade\r\n\
+        Upgrade: websocket\r\n\
+        Sec-WebSocket-Version: 13\r\n\
+        Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n",
+        addr
+    );
+    stream.write_all(handshake.as_bytes()).await.unwrap();
+    let mut buffer = [0u8; 1024];
+    let read = stream.read(&mut buffer).await.unwrap();
+    assert!(buffer[..read].starts_with(b"HTTP/1.1 101"));
+    assert_eq!(1, state.clients.len());
+
+    let body = "x".repeat(64 * 1024);
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": CONNECTION_PING,
+        "params": {"body": body},
+    });
+    let frame = frame(&request.to_string());
+
+    // Replies fill the socket buffers and then the send queue
+    tokio::time::timeout(Duration::from_secs(30), async {
+        while !state.clients.is_empty() {
+            if stream.write_all(&frame).await.is_err() {
+                break;
+            }
+        }
+        while !state.clients.is_empty() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("slow client was not evicted");
+
+    let response = warp::test::request()
+        .path("/metrics")
+        .reply(&server.routes())
+        .await;
+    let body = String::from_utf8(response.body().to_vec()).unwrap();
+    assert!(body.contains("mpc_evicted_clients_total 1"));
+}
@@ -0,0 +1,117 @@
+use std::time::Duration;
+
+use mpc_websocket::{services::*, Expiry, Server, ServerError, ServerOptions};
+use serde_json::json;
+use uuid::Uuid;
+
+mod common;
+use common::*;
+
+/// Create a server that checks for expired entries every 10ms
+/// and spawn the reaper.
+fn server(expiry: Expiry) -> Server {
+    let expiry = Expiry {
+        interval: Duration::from_millis(10),
+        ..expiry
+    };
+    let options = ServerOptions::default()
+        .tracing(false)
+        .resume_grace_period(Duration::from_secs(60))
+        .expiry(expiry);
+    let server = Server::new(options).unwrap();
+    tokio::spawn(server.reaper());
+    server
+}
+
+#[tokio::test]
+async fn session_expiry() {
+    let server = server(Expiry {
+        group: None,
+        session: Some(Duration::from_millis(50)),
+        finished_session: None,
+        ..Default::default()
+    });
+    let mut alice = connect(&server).await;
+    let mut bob = connect(&server).await;
+    let (group_id, session_id) =
+        setup_clients(&mut [&mut alice, &mut bob], "keygen", true).await;
+
+    assert_eq!(session_id, event(&mut alice, SESSION_EXPIRED_EVENT).await);
+    let response = request(
+        &mut alice,
+        SESSION_PARTICIPANTS,
+        json!([group_id, session_id]),
+    )
+    .await;
+    assert_eq!(
+        format!("session {} does not exist", session_id.as_str().unwrap()),
+        error(&response)
+    );
+}
+
+#[tokio::test]
+async fn finished_session_expiry() {
+    let server = server(Expiry {
+        group: None,
+        session: None,
+        finished_session: Some(Duration::from_millis(50)),
+        ..Default::default()
+    });
+    let mut alice = connect(&server).await;
+    let mut bob = connect(&server).await;
+    let (group_id, session_id) =
+        setup_clients(&mut [&mut alice, &mut bob], "keygen", true).await;
+
+    call(&mut alice, SESSION_FINISH, json!([group_id, session_id, 1])).await;
+    call(&mut bob, SESSION_FINISH, json!([group_id, session_id, 2])).await;
+    event(&mut alice, SESSION_CLOSED_EVENT).await;
+
+    assert_eq!(session_id, event(&mut bob, SESSION_EXPIRED_EVENT).await);
+}
+
+#[tokio::test]
+async fn group_expiry() {
+    let server = server(Expiry {
+        group: Some(Duration::from_millis(50)),
+        session: None,
+        finished_session: None,
+        ..Default::default()
+    });
+    let state = server.state();
+    let mut alice = connect(&server).await;
+    let group_id = call(
+        &mut alice,
+        GROUP_CREATE,
+        json!(["test", {"parties": 2, "threshold": 1}]),
+    )
+    .await;
+    let group_id: Uuid = serde_json::from_value(group_id).unwrap();
+
+    // Groups with connected clients are not removed
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    assert!(state.groups.contains(&group_id));
+
+    // Disconnected client holds a resume token so the group
+    // is only removed by the reaper
+    drop(alice);
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while state.groups.contains(&group_id) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("group was not removed");
+}
+
+#[test]
+fn zero_expiry_interval() {
+    let expiry = Expiry {
+        interval: Duration::ZERO,
+        ..Default::default()
+    };
+    let options = ServerOptions::default().tracing(false).expiry(expiry);
+    assert!(matches!(
+        Server::new(options),
+        Err(ServerError::ZeroExpiryInterval)
+    ));
+}
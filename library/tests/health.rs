@@ -0,0 +1,52 @@
+use std::time::Duration;
+
+use mpc_websocket::{services::*, Server, ServerOptions};
+use serde_json::{json, Value};
+use warp::{filters::BoxedFilter, Reply};
+
+mod common;
+use common::*;
+
+/// Request a health endpoint.
+async fn probe(
+    routes: &BoxedFilter<(Box<dyn Reply>,)>,
+    path: &str,
+) -> (u16, Value) {
+    let response = warp::test::request().path(path).reply(routes).await;
+    let body = serde_json::from_slice(response.body()).unwrap();
+    (response.status().as_u16(), body)
+}
+
+#[tokio::test]
+async fn health_draining() {
+    let options = ServerOptions::default()
+        .tracing(false)
+        .shutdown_timeout(Duration::from_secs(60));
+    let server = Server::new(options).unwrap();
+    let routes = server.routes();
+
+    let (status, health) = probe(&routes, "/healthz").await;
+    assert_eq!(200, status);
+    assert_eq!(json!(true), health["ready"]);
+    assert_eq!(json!(PROTOCOL_VERSION), health["protocolVersion"]);
+    let (status, _) = probe(&routes, "/readyz").await;
+    assert_eq!(200, status);
+
+    // Active session keeps the server draining
+    let mut alice = connect(&server).await;
+    let mut bob = connect(&server).await;
+    setup_clients(&mut [&mut alice, &mut bob], "keygen", true).await;
+
+    let state = server.state();
+    tokio::spawn(async move { server.shutdown().await });
+    event(&mut alice, SERVER_SHUTDOWN_EVENT).await;
+    assert!(state.is_draining());
+
+    // Liveness succeeds whilst readiness fails
+    let (status, health) = probe(&routes, "/healthz").await;
+    assert_eq!(200, status);
+    assert_eq!(json!(false), health["ready"]);
+    let (status, health) = probe(&routes, "/readyz").await;
+    assert_eq!(503, status);
+    assert_eq!(json!(false), health["ready"]);
+}
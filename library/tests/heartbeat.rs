@@ -0,0 +1,112 @@
+use std::time::Duration;
+
+use mpc_websocket::{
+    services::*, Heartbeat, Server, ServerError, ServerOptions,
+};
+use serde_json::{json, Value};
+
+mod common;
+use common::*;
+
+#[tokio::test]
+async fn connection_ping() {
+    let options = ServerOptions::default().tracing(false);
+    let server = Server::new(options).unwrap();
+    let mut client = connect(&server).await;
+
+    let value = json!({"sent": 1234});
+    assert_eq!(
+        value,
+        call(&mut client, CONNECTION_PING, value.clone()).await
+    );
+
+    let request = json!({"jsonrpc": "2.0", "id": 1, "method": CONNECTION_PING});
+    client.send_text(request.to_string()).await;
+    loop {
+        let message = recv(&mut client).await;
+        if message["id"] == 1 {
+            assert_eq!(Value::Null, message["result"]);
+            break;
+        }
+    }
+}
+
+#[tokio::test]
+async fn idle_timeout() {
+    let options = ServerOptions::default()
+        .tracing(false)
+        .resume_grace_period(Duration::ZERO)
+        .heartbeat(Heartbeat {
+            interval: None,
+            timeout: Some(Duration::from_millis(500)),
+        });
+    let server = Server::new(options).unwrap();
+
+    let mut alice = connect(&server).await;
+    let mut bob = connect(&server).await;
+
+    let group_id = call(
+        &mut alice,
+        GROUP_CREATE,
+        json!(["test", {"parties": 2, "threshold": 1}]),
+    )
+    .await;
+    call(&mut bob, GROUP_JOIN, json!(group_id)).await;
+    let joined = event(&mut alice, GROUP_MEMBER_JOINED_EVENT).await;
+
+    // Alice stays active whilst bob is idle
+    let left = loop {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": "ping",
+            "method": CONNECTION_PING,
+        });
+        alice.send_text(request.to_string()).await;
+        let message = recv(&mut alice).await;
+        if message["result"][0] == GROUP_MEMBER_LEFT_EVENT {
+            break message["result"][1].clone();
+        }
+    };
+    assert_eq!(joined["memberId"], left["memberId"]);
+    assert_eq!(json!(1), left["members"]);
+}
+
+#[test]
+fn zero_heartbeat_interval() {
+    let heartbeat = Heartbeat {
+        interval: Some(Duration::ZERO),
+        ..Default::default()
+    };
+    let options = ServerOptions::default().tracing(false).heartbeat(heartbeat);
+    assert!(matches!(
+        Server::new(options),
+        Err(ServerError::ZeroHeartbeatInterval)
+    ));
+}
+
+#[test]
+fn zero_idle_timeout() {
+    let heartbeat = Heartbeat {
+        timeout: Some(Duration::ZERO),
+        ..Default::default()
+    };
+    let options = ServerOptions::default().tracing(false).heartbeat(heartbeat);
+    assert!(matches!(
+        Server::new(options),
+        Err(ServerError::ZeroIdleTimeout)
+    ));
+}
+
+#[test]
+fn idle_timeout_too_short() {
+    let heartbeat = Heartbeat {
+        interval: Some(Duration::from_secs(30)),
+        timeout: Some(Duration::from_secs(30)),
+    };
+    let options = ServerOptions::default().tracing(false).heartbeat(heartbeat);
+    assert!(matches!(
+        Server::new(options),
+        Err(ServerError::IdleTimeoutTooShort)
+    ));
+}
@@ -0,0 +1,118 @@
+use mpc_websocket::services::*;
+use serde_json::json;
+use uuid::Uuid;
+
+mod common;
+use common::*;
+
+#[tokio::test]
+async fn session_leave() {
+    let (state, group_id, session_id) = setup(2, "keygen", 2).await;
+
+    let (response, notifications) =
+        handle(&state, 2, SESSION_LEAVE, json!([group_id, session_id])).await;
+    assert!(response.is_ok());
+    assert_eq!(
+        json!([SESSION_PARTICIPANT_LEFT_EVENT, 2]),
+        notification_event(&notifications[0])
+    );
+
+    // Slot is free to be re-used
+    let (response, _) = handle(
+        &state,
+        1,
+        SESSION_JOIN,
+        json!([group_id, session_id, "keygen"]),
+    )
+    .await;
+    assert_eq!(json!(false), result(response)["full"]);
+
+    let (response, _) =
+        handle(&state, 2, SESSION_LEAVE, json!([group_id, session_id])).await;
+    assert!(matches!(
+        service_error(response),
+        ServiceError::NotParticipant(2, _)
+    ));
+
+    let (response, _) = handle(
+        &state,
+        2,
+        SESSION_SIGNUP,
+        json!([group_id, session_id, "keygen"]),
+    )
+    .await;
+    assert_eq!(json!(2), result(response));
+}
+
+#[tokio::test]
+async fn group_leave() {
+    let (state, group_id, session_id) = setup(2, "keygen", 2).await;
+
+    let (response, notifications) =
+        handle(&state, 2, GROUP_LEAVE, json!(group_id)).await;
+    assert!(response.is_ok());
+    assert_eq!(
+        json!([SESSION_PARTICIPANT_LEFT_EVENT, 2]),
+        notification_event(&notifications[0])
+    );
+    let left = notification_event(&notifications[1]);
+    let member_id = &left[1]["memberId"];
+    assert!(serde_json::from_value::<Uuid>(member_id.clone()).is_ok());
+    assert_eq!(
+        json!([
+            GROUP_MEMBER_LEFT_EVENT,
+            {
+                "groupId": group_id,
+                "memberId": member_id,
+                "members": 1,
+                "parties": 2,
+            },
+        ]),
+        left
+    );
+
+    // No longer a member of the group
+    let (response, _) = handle(
+        &state,
+        2,
+        SESSION_JOIN,
+        json!([group_id, session_id, "keygen"]),
+    )
+    .await;
+    assert!(matches!(
+        service_error(response),
+        ServiceError::BadConnection(2, _)
+    ));
+
+    // Last member leaving removes the group
+    let (response, notifications) =
+        handle(&state, 1, GROUP_LEAVE, json!(group_id)).await;
+    assert!(response.is_ok());
+    assert!(notifications.is_empty());
+    assert!(!state.groups.contains(&group_id));
+}
+
+#[tokio::test]
+async fn leave_closes_finished_session() {
+    for method in [SESSION_LEAVE, GROUP_LEAVE] {
+        let (state, group_id, session_id) = setup(2, "keygen", 2).await;
+
+        let (response, _) =
+            handle(&state, 1, SESSION_FINISH, json!([group_id, session_id, 1]))
+                .await;
+        assert!(response.is_ok());
+
+        // Only remaining participant has finished
+        let params = if method == SESSION_LEAVE {
+            json!([group_id, session_id])
+        } else {
+            json!(group_id)
+        };
+        let (response, notifications) = handle(&state, 2, method, params).await;
+        assert!(response.is_ok());
+        assert_eq!(
+            json!([SESSION_CLOSED_EVENT, [1]]),
+            notification_event(&notifications[1])
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use std::time::Duration;
+
+use mpc_websocket::{services::*, Limits, Server, ServerOptions};
+use serde_json::json;
+
+mod common;
+use common::*;
+
+fn server(limits: Limits) -> Server {
+    let options = ServerOptions::default().tracing(false).limits(limits);
+    Server::new(options).unwrap()
+}
+
+#[tokio::test]
+async fn limit_request_size() {
+    let server = server(Limits {
+        max_request_size: Some(256),
+        ..Default::default()
+    });
+    let mut client = connect(&server).await;
+    recv(&mut client).await;
+
+    let label = "x".repeat(256);
+    let large = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": GROUP_CREATE,
+        "params": [label, {"parties": 2, "threshold": 1}],
+    });
+    client.send_text(large.to_string()).await;
+    let response = recv(&mut client).await;
+    assert_eq!(
+        "request exceeds the maximum size of 256 bytes",
+        error(&response)
+    );
+
+    // Smaller requests are still handled
+    let response = request(
+        &mut client,
+        GROUP_CREATE,
+        json!(["test", {"parties": 2, "threshold": 1}]),
+    )
+    .await;
+    assert!(response.get("error").is_none());
+}
+
+#[tokio::test]
+async fn limit_requests_per_second() {
+    let server = server(Limits {
+        requests_per_second: Some(2),
+        ..Default::default()
+    });
+    let mut client = connect(&server).await;
+
+    let params = json!(["test", {"parties": 2, "threshold": 1}]);
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": GROUP_CREATE,
+        "params": params,
+    })
+    .to_string();
+    for _ in 0..2 {
+        let group_id = call(&mut client, GROUP_CREATE, params.clone()).await;
+        assert!(group_id.is_string());
+    }
+    client.send_text(request.clone()).await;
+    assert!(rate_limited(&mut client).await);
+
+    // Tokens are replenished over time
+    tokio::time::sleep(Duration::from_millis(600)).await;
+    let group_id = call(&mut client, GROUP_CREATE, params).await;
+    assert!(group_id.is_string());
+
+    // Messages that cannot be parsed take a token
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    for _ in 0..2 {
+        client.send_text("not json").await;
+    }
+    client.send_text(request).await;
+    assert!(rate_limited(&mut client).await);
+}
+
+/// Wait for the next error response and determine if
+/// the error is for exceeding the rate limit.
+async fn rate_limited(client: &mut warp::test::WsClient) -> bool {
+    loop {
+        let message = recv(client).await;
+        if message.get("error").is_some() {
+            return error(&message)
+                .ends_with("exceeded the request rate limit");
+        }
+    }
+}
+
+#[tokio::test]
+async fn limit_groups_and_sessions() {
+    let server = server(Limits {
+        max_groups_per_connection: Some(1),
+        max_sessions_per_group: Some(1),
+        ..Default::default()
+    });
+    let mut alice = connect(&server).await;
+    let mut bob = connect(&server).await;
+
+    let params = json!(["test", {"parties": 2, "threshold": 1}]);
+    let group_id = request(&mut alice, GROUP_CREATE, params.clone()).await
+        ["result"]
+        .clone();
+    let response = request(&mut alice, GROUP_CREATE, params.clone()).await;
+    assert!(error(&response).ends_with("belongs to too many groups"));
+
+    // Joining a group again is not counted twice
+    let response = request(&mut alice, GROUP_JOIN, json!(group_id)).await;
+    assert!(response.get("error").is_none());
+
+    let other_id =
+        request(&mut bob, GROUP_CREATE, params.clone()).await["result"].clone();
+    let response = request(&mut bob, GROUP_JOIN, json!(group_id)).await;
+    assert!(error(&response).ends_with("belongs to too many groups"));
+
+    // Leaving a group frees the slot
+    request(&mut bob, GROUP_LEAVE, json!(other_id)).await;
+    let response = request(&mut bob, GROUP_JOIN, json!(group_id)).await;
+    assert!(response.get("error").is_none());
+
+    let params = json!([group_id, "keygen", null]);
+    let response = request(&mut alice, SESSION_CREATE, params.clone()).await;
+    assert!(response.get("error").is_none());
+    let response = request(&mut alice, SESSION_CREATE, params).await;
+    assert_eq!(
+        format!("group {} has too many sessions", group_id.as_str().unwrap()),
+        error(&response)
+    );
+}
@@ -0,0 +1,41 @@
+use mpc_websocket::{services::*, Server, ServerOptions};
+use serde_json::json;
+
+mod common;
+use common::*;
+
+/// Scrape the metrics endpoint.
+async fn scrape(server: &Server) -> String {
+    let response = warp::test::request()
+        .path("/metrics")
+        .reply(&server.routes())
+        .await;
+    assert_eq!(200, response.status());
+    String::from_utf8(response.body().to_vec()).unwrap()
+}
+
+#[tokio::test]
+async fn metrics_errors() {
+    let options = ServerOptions::default().tracing(false).metrics(true);
+    let server = Server::new(options).unwrap();
+    let mut alice = connect(&server).await;
+    let mut bob = connect(&server).await;
+    let mut carol = connect(&server).await;
+
+    let group_id = call(
+        &mut alice,
+        GROUP_CREATE,
+        json!(["test", {"parties": 2, "threshold": 1}]),
+    )
+    .await;
+    call(&mut bob, GROUP_JOIN, json!(group_id)).await;
+
+    // Error returned as a response that closes the connection
+    let response = request(&mut carol, GROUP_JOIN, json!(group_id)).await;
+    assert!(error(&response).ends_with("cannot accept new connections"));
+
+    let body = scrape(&server).await;
+    assert!(body.contains("mpc_requests_total{method=\"Group.join\"} 2"));
+    assert!(body.contains("mpc_errors_total{error=\"GroupFull\"} 1"));
+    assert!(body.contains("mpc_groups 1"));
+}
@@ -0,0 +1,55 @@
+use mpc_websocket::services::*;
+use serde_json::json;
+
+mod common;
+use common::*;
+
+#[tokio::test]
+async fn session_participants() {
+    let (state, group_id, session_id) = setup(3, "sign", 0).await;
+
+    // Load party numbers out of order
+    let mut notifications = Vec::new();
+    for (conn_id, party_number) in [(3, 3), (1, 1)] {
+        let (response, events) = handle(
+            &state,
+            conn_id,
+            SESSION_LOAD,
+            json!([group_id, session_id, "sign", party_number]),
+        )
+        .await;
+        result(response);
+        notifications = events;
+    }
+    assert_eq!(
+        json!([
+            SESSION_LOAD_EVENT,
+            {"sessionId": session_id, "participants": [1, 3]},
+        ]),
+        notification_event(&notifications[0])
+    );
+
+    let (response, _) = handle(
+        &state,
+        3,
+        SESSION_PARTICIPANTS,
+        json!([group_id, session_id]),
+    )
+    .await;
+    assert_eq!(
+        json!({"participants": [1, 3], "partyNumber": 3}),
+        result(response)
+    );
+
+    let (response, _) = handle(
+        &state,
+        2,
+        SESSION_PARTICIPANTS,
+        json!([group_id, session_id]),
+    )
+    .await;
+    assert_eq!(
+        json!({"participants": [1, 3], "partyNumber": null}),
+        result(response)
+    );
+}
@@ -0,0 +1,39 @@
+use std::time::Duration;
+
+use mpc_websocket::{services::*, Server, ServerOptions};
+use serde_json::json;
+
+mod common;
+use common::*;
+
+#[tokio::test]
+async fn group_presence() {
+    let options = ServerOptions::default()
+        .tracing(false)
+        .resume_grace_period(Duration::ZERO);
+    let server = Server::new(options).unwrap();
+
+    let mut alice = connect(&server).await;
+    let mut bob = connect(&server).await;
+    let mut carol = connect(&server).await;
+
+    let group_id = setup_group(&mut [&mut alice, &mut bob, &mut carol]).await;
+
+    let joined = event(&mut alice, GROUP_MEMBER_JOINED_EVENT).await;
+    assert_eq!(group_id, joined["groupId"]);
+    assert_eq!(json!(2), joined["members"]);
+    assert_eq!(json!(3), joined["parties"]);
+    let bob_id = joined["memberId"].clone();
+
+    let joined = event(&mut bob, GROUP_MEMBER_JOINED_EVENT).await;
+    assert_eq!(json!(3), joined["members"]);
+    assert_ne!(bob_id, joined["memberId"]);
+
+    // Disconnected members are removed from the group
+    drop(bob);
+    let left = event(&mut alice, GROUP_MEMBER_LEFT_EVENT).await;
+    assert_eq!(bob_id, left["memberId"]);
+    assert_eq!(json!(2), left["members"]);
+    let left = event(&mut carol, GROUP_MEMBER_LEFT_EVENT).await;
+    assert_eq!(bob_id, left["memberId"]);
+}
@@ -0,0 +1,445 @@
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use mpc_websocket::{
+    relay::{LoopbackRelay, RedisRelay, RelayBackend},
+    services::*,
+    Expiry, Result, Server, ServerOptions,
+};
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{tcp::OwnedWriteHalf, TcpListener};
+use tokio::sync::{mpsc, watch, Mutex};
+use uuid::Uuid;
+use warp::test::WsClient;
+
+mod common;
+use common::*;
+
+fn server(relay: Arc<dyn RelayBackend>) -> Server {
+    let options = ServerOptions::default().tracing(false).relay(relay);
+    let server = Server::new(options).unwrap();
+    tokio::task::spawn(server.relay());
+    server
+}
+
+/// Call a method until it succeeds whilst changes are replicated.
+async fn retry(client: &mut WsClient, method: &str, params: Value) -> Value {
+    for _ in 0..50 {
+        let response = request(client, method, params.clone()).await;
+        if response.get("error").is_none() {
+            return response["result"].clone();
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("{} did not succeed", method);
+}
+
+/// Send a `Session.signup` request returning the request identifier.
+async fn send_signup(client: &mut WsClient, params: &Value) -> String {
+    let id = Uuid::new_v4().to_string();
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "method": SESSION_SIGNUP,
+        "params": params,
+    });
+    client.send_text(request.to_string()).await;
+    id
+}
+
+/// Sign up to a session and wait until the session is ready,
+/// signing up again if the party number is rejected.
+///
+/// Returns the party number and the `sessionSignup` payload.
+async fn signup(client: &mut WsClient, params: Value) -> (Value, Value) {
+    let mut id = send_signup(client, &params).await;
+    let mut party_number = Value::Null;
+    loop {
+        let message = recv(client).await;
+        if message["id"] == id {
+            party_number = message["result"].clone();
+            continue;
+        }
+        match message["result"][0].as_str() {
+            Some(SESSION_SIGNUP_REJECTED_EVENT) => {
+                id = send_signup(client, &params).await;
+            }
+            Some(SESSION_SIGNUP_EVENT) => {
+                return (party_number, message["result"][1].clone());
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Route a key generation session between two server instances.
+async fn exchange(node1: Server, node2: Server) {
+    let mut alice = connect(&node1).await;
+    let mut bob = connect(&node2).await;
+
+    let group_id = retry(
+        &mut alice,
+        GROUP_CREATE,
+        json!(["test", {"parties": 2, "threshold": 1}]),
+    )
+    .await;
+    retry(&mut bob, GROUP_JOIN, json!(group_id)).await;
+
+    let session = retry(
+        &mut alice,
+        SESSION_CREATE,
+        json!([group_id, "keygen", null]),
+    )
+    .await;
+    let session_id = session["uuid"].clone();
+
+    // Session must be replicated before signing up
+    let params = json!([group_id, session_id, "keygen"]);
+    retry(&mut bob, SESSION_JOIN, params.clone()).await;
+
+    // Concurrent signups on both instances are assigned distinct numbers
+    let ((alice_number, alice_ready), (bob_number, bob_ready)) = tokio::join!(
+        signup(&mut alice, params.clone()),
+        signup(&mut bob, params),
+    );
+    let mut numbers = vec![alice_number.clone(), bob_number.clone()];
+    numbers.sort_by_key(|n| n.as_u64());
+    assert_eq!(vec![json!(1), json!(2)], numbers);
+
+    // Event for the session is delivered to both instances
+    let ready = json!({"sessionId": session_id, "participants": [1, 2]});
+    assert_eq!(ready, alice_ready);
+    assert_eq!(ready, bob_ready);
+
+    // Broadcast and peer messages are relayed
+    let broadcast = json!({
+        "round": 1,
+        "sender": alice_number,
+        "receiver": null,
+        "uuid": session_id,
+        "body": "broadcast",
+    });
+    retry(
+        &mut alice,
+        SESSION_MESSAGE,
+        json!([group_id, session_id, "keygen", broadcast]),
+    )
+    .await;
+    assert_eq!(broadcast, event(&mut bob, SESSION_MESSAGE_EVENT).await);
+
+    let peer = json!({
+        "round": 2,
+        "sender": bob_number,
+        "receiver": alice_number,
+        "uuid": session_id,
+        "body": "peer",
+    });
+    retry(
+        &mut bob,
+        SESSION_MESSAGE,
+        json!([group_id, session_id, "keygen", peer]),
+    )
+    .await;
+    assert_eq!(peer, event(&mut alice, SESSION_MESSAGE_EVENT).await);
+}
+
+#[tokio::test]
+async fn relay_loopback() {
+    let relay: Arc<dyn RelayBackend> = Arc::new(LoopbackRelay::default());
+    exchange(server(Arc::clone(&relay)), server(relay)).await;
+}
+
+/// Relay that can hold back published payloads and drop
+/// the subscriptions of the server instances.
+#[derive(Debug)]
+struct GatedRelay {
+    relay: LoopbackRelay,
+    held: StdMutex<Option<Vec<Vec<u8>>>>,
+    disconnect: watch::Sender<()>,
+}
+
+impl Default for GatedRelay {
+    fn default() -> Self {
+        let (disconnect, _) = watch::channel(());
+        Self {
+            relay: Default::default(),
+            held: Default::default(),
+            disconnect,
+        }
+    }
+}
+
+impl GatedRelay {
+    /// Hold back payloads until they are released or discarded.
+    fn hold(&self) {
+        *self.held.lock().unwrap() = Some(Vec::new());
+    }
+
+    /// Publish the payloads that were held back.
+    async fn release(&self) {
+        let held = self.held.lock().unwrap().take().unwrap_or_default();
+        for payload in held {
+            self.relay.publish(payload).await.unwrap();
+        }
+    }
+
+    /// Discard the payloads that were held back.
+    fn discard(&self) {
+        self.held.lock().unwrap().take();
+    }
+
+    /// Close all the subscriptions.
+    fn disconnect(&self) {
+        let _ = self.disconnect.send(());
+    }
+}
+
+#[async_trait]
+impl RelayBackend for GatedRelay {
+    async fn publish(&self, payload: Vec<u8>) -> Result<()> {
+        if let Some(held) = self.held.lock().unwrap().as_mut() {
+            held.push(payload);
+            return Ok(());
+        }
+        self.relay.publish(payload).await
+    }
+
+    async fn subscribe(&self) -> Result<mpsc::UnboundedReceiver<Vec<u8>>> {
+        let mut payloads = self.relay.subscribe().await?;
+        let mut disconnect = self.disconnect.subscribe();
+        let (tx, subscription) = mpsc::unbounded_channel();
+        tokio::task::spawn(async move {
+            loop {
+                tokio::select! {
+                    payload = payloads.recv() => {
+                        let sent = payload.map(|p| tx.send(p).is_ok());
+                        if sent != Some(true) {
+                            break;
+                        }
+                    }
+                    _ = disconnect.changed() => break,
+                }
+            }
+        });
+        Ok(subscription)
+    }
+}
+
+#[tokio::test]
+async fn relay_concurrent_signup() {
+    let relay = Arc::new(GatedRelay::default());
+    let node1 = server(Arc::clone(&relay) as Arc<dyn RelayBackend>);
+    let node2 = server(Arc::clone(&relay) as Arc<dyn RelayBackend>);
+    let mut alice = connect(&node1).await;
+    let mut bob = connect(&node2).await;
+
+    let group_id = retry(
+        &mut alice,
+        GROUP_CREATE,
+        json!(["test", {"parties": 2, "threshold": 1}]),
+    )
+    .await;
+    retry(&mut bob, GROUP_JOIN, json!(group_id)).await;
+    let session = retry(
+        &mut alice,
+        SESSION_CREATE,
+        json!([group_id, "keygen", null]),
+    )
+    .await;
+    let session_id = session["uuid"].clone();
+    let params = json!([group_id, session_id, "keygen"]);
+    retry(&mut bob, SESSION_JOIN, params.clone()).await;
+
+    // Both instances assign the same party number
+    relay.hold();
+    assert_eq!(
+        json!(1),
+        call(&mut alice, SESSION_SIGNUP, params.clone()).await
+    );
+    assert_eq!(
+        json!(1),
+        call(&mut bob, SESSION_SIGNUP, params.clone()).await
+    );
+    relay.release().await;
+
+    // Exactly one of the signups is rejected
+    let rejected = json!({"sessionId": session_id, "partyNumber": 1});
+    let (loser, winner) = tokio::select! {
+        payload = event(&mut alice, SESSION_SIGNUP_REJECTED_EVENT) => {
+            assert_eq!(rejected, payload);
+            (&mut alice, &mut bob)
+        }
+        payload = event(&mut bob, SESSION_SIGNUP_REJECTED_EVENT) => {
+            assert_eq!(rejected, payload);
+            (&mut bob, &mut alice)
+        }
+    };
+
+    assert_eq!(json!(2), call(loser, SESSION_SIGNUP, params).await);
+    let ready = json!({"sessionId": session_id, "participants": [1, 2]});
+    assert_eq!(ready, event(loser, SESSION_SIGNUP_EVENT).await);
+    assert_eq!(ready, event(winner, SESSION_SIGNUP_EVENT).await);
+}
+
+#[tokio::test]
+async fn relay_resubscribe() {
+    let relay = Arc::new(GatedRelay::default());
+    let node1 = server(Arc::clone(&relay) as Arc<dyn RelayBackend>);
+    let node2 = server(Arc::clone(&relay) as Arc<dyn RelayBackend>);
+    let mut alice = connect(&node1).await;
+    let mut bob = connect(&node2).await;
+
+    // Changes are lost whilst the subscriptions are down
+    relay.hold();
+    let group_id = call(
+        &mut alice,
+        GROUP_CREATE,
+        json!(["test", {"parties": 2, "threshold": 1}]),
+    )
+    .await;
+    relay.discard();
+    assert!(!node2
+        .state()
+        .groups
+        .contains(&serde_json::from_value(group_id.clone()).unwrap()));
+
+    // Groups are published when the instances subscribe again
+    relay.disconnect();
+    retry(&mut bob, GROUP_JOIN, json!(group_id)).await;
+}
+
+#[tokio::test]
+async fn relay_expiry_remote_clients() {
+    let relay: Arc<dyn RelayBackend> = Arc::new(LoopbackRelay::default());
+    let node1 = server(Arc::clone(&relay));
+    let expiry = Expiry {
+        interval: Duration::from_millis(10),
+        group: Some(Duration::from_millis(20)),
+        ..Default::default()
+    };
+    let options = ServerOptions::default()
+        .tracing(false)
+        .relay(relay)
+        .expiry(expiry);
+    let node2 = Server::new(options).unwrap();
+    tokio::task::spawn(node2.relay());
+    tokio::task::spawn(node2.reaper());
+
+    let mut alice = connect(&node1).await;
+    let group_id = retry(
+        &mut alice,
+        GROUP_CREATE,
+        json!(["test", {"parties": 2, "threshold": 1}]),
+    )
+    .await;
+    let group_id: Uuid = serde_json::from_value(group_id).unwrap();
+    for _ in 0..50 {
+        if node2.state().groups.contains(&group_id) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    // Idle replica is kept whilst the owning instance has clients
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(node2.state().groups.contains(&group_id));
+    assert!(node1.state().groups.contains(&group_id));
+}
+
+/// Read a command in the Redis protocol.
+async fn read_command(
+    reader: &mut BufReader<tokio::net::tcp::OwnedReadHalf>,
+) -> Option<Vec<Vec<u8>>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line).await.ok()? == 0 {
+        return None;
+    }
+    let len: usize = line.trim_end()[1..].parse().ok()?;
+    let mut args = Vec::new();
+    for _ in 0..len {
+        line.clear();
+        reader.read_line(&mut line).await.ok()?;
+        let size: usize = line.trim_end()[1..].parse().ok()?;
+        let mut arg = vec![0u8; size + 2];
+        reader.read_exact(&mut arg).await.ok()?;
+        arg.truncate(size);
+        args.push(arg);
+    }
+    Some(args)
+}
+
+fn bulk(out: &mut Vec<u8>, value: &[u8]) {
+    out.extend_from_slice(format!("${}\r\n", value.len()).as_bytes());
+    out.extend_from_slice(value);
+    out.extend_from_slice(b"\r\n");
+}
+
+/// Channels and connections subscribed to the stand-in server.
+type Subscribers = Vec<(Vec<u8>, OwnedWriteHalf)>;
+
+/// Stand-in for a Redis server supporting `SUBSCRIBE` and `PUBLISH`.
+async fn redis_stand_in() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    let subscribers: Arc<Mutex<Subscribers>> = Default::default();
+    tokio::task::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            let subscribers = Arc::clone(&subscribers);
+            tokio::task::spawn(async move {
+                let (reader, mut writer) = stream.into_split();
+                let mut reader = BufReader::new(reader);
+                while let Some(args) = read_command(&mut reader).await {
+                    match args[0].to_ascii_uppercase().as_slice() {
+                        b"SUBSCRIBE" => {
+                            let mut out = b"*3\r\n".to_vec();
+                            bulk(&mut out, b"subscribe");
+                            bulk(&mut out, &args[1]);
+                            out.extend_from_slice(b":1\r\n");
+                            writer.write_all(&out).await.unwrap();
+                            subscribers
+                                .lock()
+                                .await
+                                .push((args[1].clone(), writer));
+                            return;
+                        }
+                        b"PUBLISH" => {
+                            let mut subscribers = subscribers.lock().await;
+                            let mut count = 0;
+                            for (channel, subscriber) in subscribers.iter_mut()
+                            {
+                                if channel == &args[1] {
+                                    let mut out = b"*3\r\n".to_vec();
+                                    bulk(&mut out, b"message");
+                                    bulk(&mut out, &args[1]);
+                                    bulk(&mut out, &args[2]);
+                                    subscriber.write_all(&out).await.unwrap();
+                                    count += 1;
+                                }
+                            }
+                            let reply = format!(":{}\r\n", count);
+                            writer.write_all(reply.as_bytes()).await.unwrap();
+                        }
+                        _ => {
+                            writer
+                                .write_all(b"-ERR unknown\r\n")
+                                .await
+                                .unwrap();
+                        }
+                    }
+                }
+            });
+        }
+    });
+    addr
+}
+
+#[tokio::test]
+async fn relay_redis() {
+    let addr = redis_stand_in().await;
+    let node1 = server(Arc::new(RedisRelay::new(addr.clone(), "mpc")));
+    let node2 = server(Arc::new(RedisRelay::new(addr, "mpc")));
+    exchange(node1, node2).await;
+}
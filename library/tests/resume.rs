@@ -0,0 +1,113 @@
+use std::time::Duration;
+
+use mpc_websocket::{services::*, Server, ServerOptions};
+use serde_json::{json, Value};
+
+mod common;
+use common::*;
+
+fn server(grace_period: Duration) -> Server {
+    let options = ServerOptions::default()
+        .tracing(false)
+        .resume_grace_period(grace_period);
+    Server::new(options).unwrap()
+}
+
+/// Create a group and key generation session that the
+/// first client has signed up to.
+async fn setup(
+    alice: &mut warp::test::WsClient,
+    bob: &mut warp::test::WsClient,
+) -> (Value, Value, Value) {
+    let (group_id, session_id) =
+        setup_clients(&mut [&mut *alice, &mut *bob], "keygen", false).await;
+    let params = json!([group_id, session_id, "keygen"]);
+    call(alice, SESSION_SIGNUP, params).await;
+    let token = event(alice, CONNECTION_TOKEN_EVENT).await;
+    (group_id, session_id, token)
+}
+
+#[tokio::test]
+async fn resume_connection() {
+    let server = server(Duration::from_secs(60));
+    let mut alice = connect(&server).await;
+    let mut bob = connect(&server).await;
+    let (group_id, session_id, token) = setup(&mut alice, &mut bob).await;
+
+    // Active connections cannot be taken over
+    let mut mallory = connect(&server).await;
+    let response = request(&mut mallory, CONNECTION_RESUME, json!(token)).await;
+    assert_eq!(
+        "connection for the resume token is still active",
+        error(&response)
+    );
+
+    drop(alice);
+    let mut alice = connect(&server).await;
+    let groups = call(&mut alice, CONNECTION_RESUME, json!(token)).await;
+    assert_eq!(json!([group_id]), groups);
+
+    // Group membership and party signup belong to the new connection
+    let participants = call(
+        &mut alice,
+        SESSION_PARTICIPANTS,
+        json!([group_id, session_id]),
+    )
+    .await;
+    assert_eq!(json!([1]), participants["participants"]);
+    assert_eq!(json!(1), participants["partyNumber"]);
+
+    // Token was transferred to the new connection
+    let response = request(&mut mallory, CONNECTION_RESUME, json!(token)).await;
+    assert_eq!(
+        "connection for the resume token is still active",
+        error(&response)
+    );
+}
+
+#[tokio::test]
+async fn resume_connection_in_use() {
+    let server = server(Duration::from_secs(60));
+    let mut alice = connect(&server).await;
+    let mut bob = connect(&server).await;
+    let (group_id, _, token) = setup(&mut alice, &mut bob).await;
+    drop(alice);
+
+    // Member of a group cannot resume another connection
+    let response = request(&mut bob, CONNECTION_RESUME, json!(token)).await;
+    assert!(
+        error(&response).ends_with("is already in use and cannot be resumed")
+    );
+
+    // Nor can a connection that was issued a token of its own
+    let mut carol = connect(&server).await;
+    let other = call(
+        &mut carol,
+        GROUP_CREATE,
+        json!(["other", {"parties": 2, "threshold": 1}]),
+    )
+    .await;
+    assert_ne!(group_id, other);
+    call(&mut carol, GROUP_LEAVE, json!(other)).await;
+    let response = request(&mut carol, CONNECTION_RESUME, json!(token)).await;
+    assert!(
+        error(&response).ends_with("is already in use and cannot be resumed")
+    );
+}
+
+#[tokio::test]
+async fn resume_grace_period_expired() {
+    let server = server(Duration::from_millis(50));
+    let mut alice = connect(&server).await;
+    let mut bob = connect(&server).await;
+    let (_, _, token) = setup(&mut alice, &mut bob).await;
+    drop(alice);
+
+    // Pruned once the grace period has elapsed
+    let left = event(&mut bob, GROUP_MEMBER_LEFT_EVENT).await;
+    assert_eq!(json!(1), left["members"]);
+
+    let mut alice = connect(&server).await;
+    let response = request(&mut alice, CONNECTION_RESUME, json!(token)).await;
+    assert_eq!("resume token is not valid", error(&response));
+}
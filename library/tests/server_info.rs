@@ -0,0 +1,51 @@
+use mpc_websocket::{services::*, Limits, Server, ServerOptions};
+use serde_json::{json, Value};
+
+mod common;
+use common::*;
+
+#[tokio::test]
+async fn server_info() {
+    let limits = Limits {
+        max_sessions_per_group: Some(4),
+        ..Default::default()
+    };
+    let options = ServerOptions::default().tracing(false).limits(limits);
+    let server = Server::new(options).unwrap();
+    let mut client = connect_path(&server, "/mpc").await;
+
+    let request = json!({"jsonrpc": "2.0", "id": 1, "method": SERVER_INFO});
+    client.send_text(request.to_string()).await;
+    let info = loop {
+        let message = recv(&mut client).await;
+        if message["id"] == 1 {
+            break message["result"].clone();
+        }
+    };
+    let info: ServerInfo = serde_json::from_value(info).unwrap();
+    assert_eq!(env!("CARGO_PKG_VERSION"), info.version);
+    assert_eq!(PROTOCOL_VERSION, info.protocol);
+    assert_eq!(METHODS.len(), info.methods.len());
+    assert!(info.methods.iter().any(|m| m == SERVER_INFO));
+    assert_eq!(EVENTS.len(), info.events.len());
+    assert!(info.events.iter().any(|e| e == SESSION_MESSAGE_EVENT));
+    assert_eq!(Some(4), info.limits.max_sessions_per_group);
+}
+
+#[tokio::test]
+async fn protocol_version() {
+    let options = ServerOptions::default().tracing(false);
+    let server = Server::new(options).unwrap();
+
+    let path = format!("/mpc?protocol={}", PROTOCOL_VERSION);
+    let mut client = connect_path(&server, &path).await;
+    let message = recv(&mut client).await;
+    assert_eq!(json!(CONNECTION_CHALLENGE_EVENT), message["result"][0]);
+
+    let path = format!("/mpc?protocol={}", PROTOCOL_VERSION + 1);
+    let mut client = connect_path(&server, &path).await;
+    let message = recv(&mut client).await;
+    assert_eq!(Value::Null, message["id"]);
+    assert_eq!(json!(CLOSE_CONNECTION), message["error"]["data"]);
+    assert!(client.recv_closed().await.is_ok());
+}
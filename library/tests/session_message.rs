@@ -0,0 +1,253 @@
+use mpc_websocket::{services::*, Notification};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+mod common;
+use common::*;
+
+fn message(session_id: &Uuid, sender: u16, receiver: Option<u16>) -> Value {
+    json!({
+        "round": 1,
+        "sender": sender,
+        "receiver": receiver,
+        "uuid": session_id,
+        "body": null,
+    })
+}
+
+#[tokio::test]
+async fn session_message_from_sender() {
+    let (state, group_id, session_id) = setup(3, "keygen", 2).await;
+
+    let (response, notifications) = handle(
+        &state,
+        1,
+        SESSION_MESSAGE,
+        json!([
+            group_id,
+            session_id,
+            "keygen",
+            message(&session_id, 1, None)
+        ]),
+    )
+    .await;
+    assert!(response.is_ok());
+    assert!(matches!(notifications[0], Notification::Session { .. }));
+
+    let (response, notifications) = handle(
+        &state,
+        2,
+        SESSION_MESSAGE,
+        json!([
+            group_id,
+            session_id,
+            "keygen",
+            message(&session_id, 2, Some(1))
+        ]),
+    )
+    .await;
+    assert!(response.is_ok());
+    assert!(matches!(
+        &notifications[0],
+        Notification::Relay { messages } if messages[0].0 == 1
+    ));
+}
+
+#[tokio::test]
+async fn session_message_spoofed_broadcast() {
+    let (state, group_id, session_id) = setup(3, "keygen", 2).await;
+
+    let (response, notifications) = handle(
+        &state,
+        1,
+        SESSION_MESSAGE,
+        json!([
+            group_id,
+            session_id,
+            "keygen",
+            message(&session_id, 2, None)
+        ]),
+    )
+    .await;
+    assert!(matches!(
+        service_error(response),
+        ServiceError::BadSender(2)
+    ));
+    assert!(notifications.is_empty());
+}
+
+#[tokio::test]
+async fn session_message_spoofed_peer_to_peer() {
+    let (state, group_id, session_id) = setup(3, "keygen", 2).await;
+
+    let (response, notifications) = handle(
+        &state,
+        2,
+        SESSION_MESSAGE,
+        json!([
+            group_id,
+            session_id,
+            "keygen",
+            message(&session_id, 1, Some(2))
+        ]),
+    )
+    .await;
+    assert!(matches!(
+        service_error(response),
+        ServiceError::BadSender(1)
+    ));
+    assert!(notifications.is_empty());
+}
+
+#[tokio::test]
+async fn session_message_not_participant() {
+    let (state, group_id, session_id) = setup(3, "keygen", 2).await;
+
+    // Group member that has not signed up to the session
+    let (response, notifications) = handle(
+        &state,
+        3,
+        SESSION_MESSAGE,
+        json!([
+            group_id,
+            session_id,
+            "keygen",
+            message(&session_id, 1, None)
+        ]),
+    )
+    .await;
+    assert!(matches!(
+        service_error(response),
+        ServiceError::NotParticipant(3, id) if id == session_id
+    ));
+    assert!(notifications.is_empty());
+
+    // Connection that is not a member of the group
+    let (response, _) = handle(
+        &state,
+        4,
+        SESSION_MESSAGE,
+        json!([
+            group_id,
+            session_id,
+            "keygen",
+            message(&session_id, 3, None)
+        ]),
+    )
+    .await;
+    assert!(matches!(
+        service_error(response),
+        ServiceError::BadConnection(4, _)
+    ));
+}
+
+#[tokio::test]
+async fn session_message_aborted() {
+    let (state, group_id, session_id) = setup(3, "keygen", 2).await;
+
+    // Party number must belong to the caller
+    let (response, _) = handle(
+        &state,
+        1,
+        SESSION_ABORT,
+        json!([group_id, session_id, 2, "failed", "keygen failed"]),
+    )
+    .await;
+    assert!(matches!(service_error(response), ServiceError::BadParty(2)));
+
+    let (response, notifications) = handle(
+        &state,
+        2,
+        SESSION_ABORT,
+        json!([group_id, session_id, 2, "failed", "keygen failed"]),
+    )
+    .await;
+    assert!(response.is_ok());
+    match &notifications[0] {
+        Notification::Session { response, .. } => assert_eq!(
+            &json!([
+                SESSION_ABORTED_EVENT,
+                {
+                    "sessionId": session_id,
+                    "partyNumber": 2,
+                    "code": "failed",
+                    "message": "keygen failed",
+                },
+            ]),
+            response.result().as_ref().unwrap()
+        ),
+        _ => panic!("expected session notification"),
+    }
+
+    let (response, notifications) = handle(
+        &state,
+        1,
+        SESSION_MESSAGE,
+        json!([
+            group_id,
+            session_id,
+            "keygen",
+            message(&session_id, 1, None)
+        ]),
+    )
+    .await;
+    assert!(matches!(
+        service_error(response),
+        ServiceError::SessionAborted(id) if id == session_id
+    ));
+    assert!(notifications.is_empty());
+}
+
+#[tokio::test]
+async fn session_blame() {
+    let (state, group_id, session_id) = setup(3, "keygen", 2).await;
+
+    // Culprits must be participants in the session
+    let (response, _) = handle(
+        &state,
+        1,
+        SESSION_BLAME,
+        json!([
+            group_id,
+            session_id,
+            1,
+            {"round": 2, "kind": "bad decommit", "culprits": [3]}
+        ]),
+    )
+    .await;
+    assert!(matches!(
+        service_error(response),
+        ServiceError::PartyDoesNotExist(3)
+    ));
+
+    let (response, notifications) = handle(
+        &state,
+        1,
+        SESSION_BLAME,
+        json!([
+            group_id,
+            session_id,
+            1,
+            {"round": 2, "kind": "bad decommit", "culprits": [2]}
+        ]),
+    )
+    .await;
+    assert!(response.is_ok());
+    match &notifications[0] {
+        Notification::Group { response, .. } => assert_eq!(
+            &json!([
+                SESSION_BLAME_EVENT,
+                {
+                    "sessionId": session_id,
+                    "partyNumber": 1,
+                    "round": 2,
+                    "kind": "bad decommit",
+                    "culprits": [2],
+                    "message": "",
+                },
+            ]),
+            response.result().as_ref().unwrap()
+        ),
+        _ => panic!("expected group notification"),
+    }
+}
@@ -0,0 +1,97 @@
+use std::time::Duration;
+
+use mpc_websocket::{services::*, Server, ServerOptions};
+use serde_json::json;
+
+mod common;
+use common::*;
+
+#[tokio::test]
+async fn shutdown_drains_sessions() {
+    let options = ServerOptions::default()
+        .tracing(false)
+        .shutdown_timeout(Duration::from_secs(60));
+    let server = Server::new(options).unwrap();
+    let routes = server.routes();
+    let state = server.state();
+
+    let mut alice = connect(&server).await;
+    let mut bob = connect(&server).await;
+    let (group_id, session_id) =
+        setup_clients(&mut [&mut alice, &mut bob], "keygen", true).await;
+
+    let shutdown = tokio::spawn(async move { server.shutdown().await });
+    assert_eq!(json!(60), event(&mut alice, SERVER_SHUTDOWN_EVENT).await);
+    assert_eq!(json!(60), event(&mut bob, SERVER_SHUTDOWN_EVENT).await);
+
+    // New groups and sessions are rejected
+    let response = request(
+        &mut alice,
+        GROUP_CREATE,
+        json!(["test", {"parties": 2, "threshold": 1}]),
+    )
+    .await;
+    assert_eq!("server is shutting down", error(&response));
+    let response = request(
+        &mut alice,
+        SESSION_CREATE,
+        json!([group_id, "keygen", null]),
+    )
+    .await;
+    assert_eq!("server is shutting down", error(&response));
+
+    // New connections are refused
+    let mut carol = warp::test::ws()
+        .path("/mpc")
+        .handshake(routes)
+        .await
+        .unwrap();
+    assert!(carol.recv_closed().await.is_ok());
+
+    // Active session is given time to finish
+    assert_eq!(2, state.clients.len());
+    call(&mut alice, SESSION_FINISH, json!([group_id, session_id, 1])).await;
+    call(&mut bob, SESSION_FINISH, json!([group_id, session_id, 2])).await;
+
+    tokio::time::timeout(Duration::from_secs(5), shutdown)
+        .await
+        .expect("shutdown did not complete")
+        .unwrap();
+    assert!(state.clients.is_empty());
+}
+
+#[tokio::test]
+async fn shutdown_participant_disconnect() {
+    let options = ServerOptions::default()
+        .tracing(false)
+        .resume_grace_period(Duration::from_secs(60))
+        .shutdown_timeout(Duration::from_secs(60));
+    let server = Server::new(options).unwrap();
+    let state = server.state();
+
+    let mut alice = connect(&server).await;
+    let mut bob = connect(&server).await;
+    let (group_id, session_id) =
+        setup_clients(&mut [&mut alice, &mut bob], "keygen", true).await;
+
+    let shutdown = tokio::spawn(async move { server.shutdown().await });
+    event(&mut alice, SERVER_SHUTDOWN_EVENT).await;
+
+    // Departed participant is pruned without waiting for a resume
+    drop(bob);
+    assert_eq!(
+        json!(2),
+        event(&mut alice, SESSION_PARTICIPANT_LEFT_EVENT).await
+    );
+    let left = event(&mut alice, GROUP_MEMBER_LEFT_EVENT).await;
+    assert_eq!(json!(1), left["members"]);
+
+    // Session closes once the remaining participant finishes
+    call(&mut alice, SESSION_FINISH, json!([group_id, session_id, 1])).await;
+    assert_eq!(json!([1]), event(&mut alice, SESSION_CLOSED_EVENT).await);
+    tokio::time::timeout(Duration::from_secs(5), shutdown)
+        .await
+        .expect("shutdown did not complete")
+        .unwrap();
+    assert!(state.clients.is_empty());
+}
@@ -0,0 +1,99 @@
+use json_rpc2::Error;
+use mpc_websocket::{services::*, Notification, ServerError, SessionKind};
+use serde_json::{json, Value};
+
+mod common;
+use common::*;
+
+/// Events sent to the clients in a session.
+fn session_events(notifications: &[Notification]) -> Vec<Value> {
+    notifications
+        .iter()
+        .filter(|n| matches!(n, Notification::Session { .. }))
+        .map(notification_event)
+        .collect()
+}
+
+#[tokio::test]
+async fn session_signup_idempotent() {
+    let (state, group_id, session_id) = setup(3, "sign", 0).await;
+    let params = json!([group_id, session_id, "sign"]);
+
+    for _ in 0..2 {
+        let (response, notifications) =
+            handle(&state, 1, SESSION_SIGNUP, params.clone()).await;
+        assert_eq!(json!(1), result(response));
+        assert!(session_events(&notifications).is_empty());
+    }
+
+    let (response, notifications) =
+        handle(&state, 2, SESSION_SIGNUP, params.clone()).await;
+    assert_eq!(json!(2), result(response));
+    assert_eq!(
+        vec![json!([
+            SESSION_SIGNUP_EVENT,
+            {"sessionId": session_id, "participants": [1, 2]},
+        ])],
+        session_events(&notifications)
+    );
+
+    // Repeated signup does not emit the event again
+    let (response, notifications) =
+        handle(&state, 2, SESSION_SIGNUP, params).await;
+    assert_eq!(json!(2), result(response));
+    assert!(session_events(&notifications).is_empty());
+}
+
+#[tokio::test]
+async fn session_signup_full() {
+    let (state, group_id, session_id) = setup(3, "sign", 0).await;
+    let params = json!([group_id, session_id, "sign"]);
+
+    for conn_id in [1, 2] {
+        let (response, _) =
+            handle(&state, conn_id, SESSION_SIGNUP, params.clone()).await;
+        result(response);
+    }
+
+    let (response, _) = handle(&state, 3, SESSION_SIGNUP, params).await;
+    match response {
+        Err(Error::Boxed(e)) => assert!(matches!(
+            *e.downcast::<ServerError>().unwrap(),
+            ServerError::SessionFull(id) if id == session_id
+        )),
+        _ => panic!("expected session full error"),
+    }
+}
+
+#[tokio::test]
+async fn session_kind_mismatch() {
+    let (state, group_id, session_id) = setup(3, "sign", 0).await;
+    let message = json!({
+        "round": 1,
+        "sender": 1,
+        "receiver": null,
+        "uuid": session_id,
+        "body": null,
+    });
+
+    for (method, params) in [
+        (SESSION_JOIN, json!([group_id, session_id, "keygen"])),
+        (SESSION_SIGNUP, json!([group_id, session_id, "keygen"])),
+        (SESSION_LOAD, json!([group_id, session_id, "keygen", 1])),
+        (
+            SESSION_MESSAGE,
+            json!([group_id, session_id, "keygen", message]),
+        ),
+    ] {
+        let (response, notifications) = handle(&state, 1, method, params).await;
+        assert!(matches!(
+            service_error(response),
+            ServiceError::KindMismatch(
+                id,
+                SessionKind::Sign,
+                SessionKind::Keygen,
+            ) if id == session_id
+        ));
+        assert!(notifications.is_empty());
+    }
+}
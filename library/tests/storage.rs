@@ -0,0 +1,188 @@
+use std::sync::Arc;
+
+use mpc_websocket::{services::*, storage::FileStorage, Server, ServerOptions};
+use serde_json::json;
+use uuid::Uuid;
+
+mod common;
+use common::*;
+
+fn server(dir: &std::path::Path) -> Server {
+    let storage = FileStorage::new(dir.to_path_buf()).unwrap();
+    let options = ServerOptions::default()
+        .tracing(false)
+        .storage(Arc::new(storage));
+    Server::new(options).unwrap()
+}
+
+#[tokio::test]
+async fn storage_restart() {
+    let dir = std::env::temp_dir().join(Uuid::new_v4().to_string());
+
+    let (group_id, session_id) = {
+        let state = server(&dir).state();
+        let group_id: Uuid = serde_json::from_value(result(
+            handle(
+                &state,
+                1,
+                GROUP_CREATE,
+                json!(["test", {"parties": 2, "threshold": 1}]),
+            )
+            .await
+            .0,
+        ))
+        .unwrap();
+        let session = result(
+            handle(
+                &state,
+                1,
+                SESSION_CREATE,
+                json!([group_id, "keygen", null]),
+            )
+            .await
+            .0,
+        );
+        let session_id: Uuid =
+            serde_json::from_value(session["uuid"].clone()).unwrap();
+        (group_id, session_id)
+    };
+
+    // Groups and sessions are loaded by a new server
+    let state = server(&dir).state();
+    for (conn_id, party_number) in [(10, 1), (11, 2)] {
+        result(handle(&state, conn_id, GROUP_JOIN, json!(group_id)).await.0);
+        let number = result(
+            handle(
+                &state,
+                conn_id,
+                SESSION_LOAD,
+                json!([group_id, session_id, "keygen", party_number]),
+            )
+            .await
+            .0,
+        );
+        assert_eq!(json!(party_number), number);
+    }
+
+    let session = result(
+        handle(
+            &state,
+            10,
+            SESSION_JOIN,
+            json!([group_id, session_id, "keygen"]),
+        )
+        .await
+        .0,
+    );
+    assert_eq!(json!(true), session["full"]);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn storage_aborted_session() {
+    let dir = std::env::temp_dir().join(Uuid::new_v4().to_string());
+
+    let (group_id, session_id) = {
+        let state = server(&dir).state();
+        let group_id: Uuid = serde_json::from_value(result(
+            handle(
+                &state,
+                1,
+                GROUP_CREATE,
+                json!(["test", {"parties": 2, "threshold": 1}]),
+            )
+            .await
+            .0,
+        ))
+        .unwrap();
+        let session = result(
+            handle(
+                &state,
+                1,
+                SESSION_CREATE,
+                json!([group_id, "keygen", null]),
+            )
+            .await
+            .0,
+        );
+        let session_id: Uuid =
+            serde_json::from_value(session["uuid"].clone()).unwrap();
+        result(
+            handle(
+                &state,
+                1,
+                SESSION_SIGNUP,
+                json!([group_id, session_id, "keygen"]),
+            )
+            .await
+            .0,
+        );
+        let (response, _) = handle(
+            &state,
+            1,
+            SESSION_ABORT,
+            json!([group_id, session_id, 1, "timeout", "timed out"]),
+        )
+        .await;
+        assert!(response.is_ok());
+        (group_id, session_id)
+    };
+
+    // Aborted sessions remain aborted when loaded by a new server
+    let state = server(&dir).state();
+    result(handle(&state, 10, GROUP_JOIN, json!(group_id)).await.0);
+    result(
+        handle(
+            &state,
+            10,
+            SESSION_LOAD,
+            json!([group_id, session_id, "keygen", 1]),
+        )
+        .await
+        .0,
+    );
+    let (response, _) = handle(
+        &state,
+        10,
+        SESSION_ABORT,
+        json!([group_id, session_id, 1, "timeout", "timed out"]),
+    )
+    .await;
+    assert!(matches!(
+        service_error(response),
+        ServiceError::SessionAborted(id) if id == session_id
+    ));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn storage_shutdown() {
+    let dir = std::env::temp_dir().join(Uuid::new_v4().to_string());
+    let node = server(&dir);
+    let state = node.state();
+    let mut alice = connect(&node).await;
+    let group_id = call(
+        &mut alice,
+        GROUP_CREATE,
+        json!(["test", {"parties": 2, "threshold": 1}]),
+    )
+    .await;
+    let group_id: Uuid = serde_json::from_value(group_id).unwrap();
+
+    // Connections closed by the shutdown prune the group
+    node.shutdown().await;
+    tokio::time::timeout(std::time::Duration::from_secs(5), async {
+        while state.groups.contains(&group_id) {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("group was not pruned");
+
+    // Group is kept in storage for the next server
+    assert!(server(&dir).state().groups.contains(&group_id));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,134 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::net::{SocketAddr, TcpListener};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use mpc_websocket::{
+    ClientAuth, Server, ServerError, ServerOptions, TlsOptions,
+};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::oneshot;
+use tokio_rustls::{
+    rustls::{internal::pemfile, ClientConfig},
+    webpki::DNSNameRef,
+    TlsConnector,
+};
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/tls")
+        .join(name)
+}
+
+fn tls() -> TlsOptions {
+    TlsOptions::new(fixture("server.pem"), fixture("server-key.pem"))
+}
+
+fn options(tls: TlsOptions) -> ServerOptions {
+    ServerOptions::default().tracing(false).tls(tls)
+}
+
+#[test]
+fn tls_missing_files() {
+    let missing = fixture("missing.pem");
+    let cases = vec![
+        TlsOptions::new(missing.clone(), fixture("server-key.pem")),
+        TlsOptions::new(fixture("server.pem"), missing.clone()),
+        tls().client_auth(ClientAuth::Optional(missing.clone())),
+        tls().client_auth(ClientAuth::Required(missing.clone())),
+    ];
+    for tls in cases {
+        let result = Server::new(options(tls));
+        assert!(matches!(
+            result,
+            Err(ServerError::NotFile(path)) if path == missing
+        ));
+    }
+    assert!(Server::new(options(tls())).is_ok());
+}
+
+/// Run a server on an unused port until the returned sender is dropped.
+async fn serve(tls: TlsOptions) -> (SocketAddr, oneshot::Sender<()>) {
+    let addr = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let server = Server::new(options(tls)).unwrap();
+    let (shutdown, signal) = oneshot::channel::<()>();
+    tokio::spawn(async move {
+        let signal = async move {
+            let _ = signal.await;
+        };
+        server.run_until(addr, signal).await.unwrap();
+    });
+    for _ in 0..50 {
+        if TcpStream::connect(addr).await.is_ok() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    (addr, shutdown)
+}
+
+/// Perform a websocket handshake over TLS and return the status line.
+async fn handshake(
+    addr: SocketAddr,
+    client_cert: bool,
+) -> std::io::Result<String> {
+    let mut config = ClientConfig::new();
+    let mut ca = BufReader::new(File::open(fixture("ca.pem"))?);
+    config.root_store.add_pem_file(&mut ca).unwrap();
+    if client_cert {
+        let mut cert = BufReader::new(File::open(fixture("client.pem"))?);
+        let mut key = BufReader::new(File::open(fixture("client-key.pem"))?);
+        let certs = pemfile::certs(&mut cert).unwrap();
+        let key = pemfile::pkcs8_private_keys(&mut key).unwrap().remove(0);
+        config.set_single_client_cert(certs, key).unwrap();
+    }
+
+    let connector = TlsConnector::from(Arc::new(config));
+    let domain = DNSNameRef::try_from_ascii_str("localhost").unwrap();
+    let stream = TcpStream::connect(addr).await?;
+    let mut stream = connector.connect(domain, stream).await?;
+
+    let request = format!(
+        "GET /mpc HTTP/1.1\r\n\
+        Host: localhost:{}\r\n\
+        Connection: Upgrade\r\n\
+        Upgrade: websocket\r\n\
+        Sec-WebSocket-Version: 13\r\n\
+        Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n",
+        addr.port()
+    );
+    stream.write_all(request.as_bytes()).await?;
+    let mut buffer = [0u8; 1024];
+    let read = stream.read(&mut buffer).await?;
+    let response = String::from_utf8_lossy(&buffer[..read]);
+    Ok(response.lines().next().unwrap_or_default().to_string())
+}
+
+#[tokio::test]
+async fn tls_websocket_handshake() {
+    let (addr, shutdown) = serve(tls()).await;
+    let status = handshake(addr, false).await.unwrap();
+    assert!(status.starts_with("HTTP/1.1 101"));
+    drop(shutdown);
+}
+
+#[tokio::test]
+async fn tls_client_auth_required() {
+    let ca = fixture("ca.pem");
+    let (addr, shutdown) =
+        serve(tls().client_auth(ClientAuth::Required(ca))).await;
+
+    // Connections without a client certificate are rejected
+    let status = handshake(addr, false).await.unwrap_or_default();
+    assert!(!status.starts_with("HTTP/1.1 101"));
+
+    let status = handshake(addr, true).await.unwrap();
+    assert!(status.starts_with("HTTP/1.1 101"));
+    drop(shutdown);
+}
@@ -0,0 +1,9 @@
+use mpc_websocket::{Server, ServerOptions};
+
+#[test]
+fn multiple_servers_with_tracing() {
+    // Global subscriber is only initialized once
+    let options = ServerOptions::default().tracing(true);
+    Server::new(options.clone()).unwrap();
+    Server::new(options).unwrap();
+}
@@ -23,6 +23,10 @@ export type RpcError = {
   data?: any;
 };
 
+// Version of the server protocol implemented by this client,
+// requested using the `protocol` query parameter when connecting.
+export const PROTOCOL_VERSION = 1;
+
 type PromiseCache = {
   resolve: (message: unknown) => void;
   reject: (reason: any) => void;
@@ -51,7 +55,9 @@ export class WebSocketClient extends EventEmitter {
     if (this.websocket) {
       this.websocket.close();
     }
-    this.websocket = new WebSocket(url);
+    const target = new URL(url);
+    target.searchParams.set('protocol', PROTOCOL_VERSION.toString());
+    this.websocket = new WebSocket(target.toString());
     this.websocket.onopen = (/* event */) => {
       this.connected = true;
 
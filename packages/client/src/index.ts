@@ -23,6 +23,7 @@ export type EcdsaWorker = {
     index: number,
     participants: number[],
     localKey: LocalKey,
+    signups: number[],
   ): Promise<Signer>;
 
   // Value is a `Uint8Array` wrapped into a sequence
@@ -92,6 +93,33 @@ export type Session = {
   value?: any;
 };
 
+// Payload for the events emitted when all the parties
+// have signed up to or loaded into a session.
+export type SessionReady = {
+  sessionId: string;
+  // Sorted party signup numbers.
+  participants: number[];
+};
+
+// Parties accused of causing a protocol round to fail,
+// returned by `blame()` on a key generator or signer.
+export type Blame = {
+  round: number;
+  kind: string;
+  // Party signup numbers of the accused parties.
+  culprits: number[];
+  message: string;
+};
+
+// Result of the `Server.info` method.
+export type ServerInfo = {
+  version: string;
+  protocol: number;
+  methods: string[];
+  events: string[];
+  limits: { [key: string]: number | null };
+};
+
 // State for party signup round during keygen.
 export type PartySignupInfo = {
   parameters: Parameters;
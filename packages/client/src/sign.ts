@@ -25,6 +25,9 @@ import {
  * This is required to be able to instantiate the webassembly
  * signing state machine correctly.
  *
+ * Returns the key share indices and the party signup numbers
+ * for this session of the participants sorted by party signup number.
+ *
  * @param info - The session information.
  * @param keyShare - The key share.
  * @param stream - The stream for sending messages.
@@ -37,7 +40,7 @@ async function getParticipants(
   stream: StreamTransport,
   sink: SinkTransport,
   onTransition: (previousRound: string, current: string) => void,
-): Promise<number[]> {
+): Promise<[number[], number[]]> {
   const rounds: Round[] = [
     {
       name: 'SIGN_ROUND_0',
@@ -51,9 +54,9 @@ async function getParticipants(
         const indexMessage: Message = {
           round,
           uuid: info.sessionId,
-          sender: index,
+          sender: info.partySignup.number,
           receiver: null,
-          body: info.partySignup.number,
+          body: index,
         };
 
         return [round, [indexMessage]];
@@ -63,8 +66,8 @@ async function getParticipants(
 
   const finalizer = {
     name: 'SIGN_PARTICIPANTS',
-    finalize: async (incoming: Message[]) => {
-      const participants = incoming.map((msg) => [msg.sender, msg.body]);
+    finalize: async (incoming: Message[]): Promise<[number[], number[]]> => {
+      const participants = incoming.map((msg) => [msg.body, msg.sender]);
       participants.push([keyShare.localKey.i, info.partySignup.number]);
       // NOTE: Must be sorted by party signup number to ensure
       // NOTE: the party signup indices correspond to the correct
@@ -81,11 +84,14 @@ async function getParticipants(
         return 0;
       });
 
-      return participants.map((item) => item[0]);
+      return [
+        participants.map((item) => item[0]),
+        participants.map((item) => item[1]),
+      ];
     },
   };
 
-  const handler = new RoundBased<number[]>(
+  const handler = new RoundBased<[number[], number[]]>(
     rounds,
     finalizer,
     onTransition,
@@ -248,7 +254,7 @@ async function signMessage(
   message: Uint8Array,
   onTransition: (previousRound: string, current: string) => void,
 ): Promise<SignMessage> {
-  const participants = await getParticipants(
+  const [participants, signups] = await getParticipants(
     info,
     keyShare,
     stream,
@@ -261,6 +267,7 @@ async function signMessage(
     info.partySignup.number,
     participants,
     keyShare.localKey,
+    signups,
   );
 
   await offlineStage(signer, stream, sink, onTransition);
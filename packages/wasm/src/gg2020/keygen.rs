@@ -1,11 +1,12 @@
 //! Key generation.
 use curv::elliptic::curves::secp256_k1::Secp256k1;
 use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::state_machine::keygen::{
-    Keygen, LocalKey, ProtocolMessage,
+    Error, Keygen, LocalKey, ProceedError, ProtocolMessage,
 };
 
 use wasm_bindgen::prelude::*;
 
+use super::Blame;
 use crate::Parameters;
 use serde::{Deserialize, Serialize};
 
@@ -63,10 +64,25 @@ pub struct KeyShare {
     pub address: String,
 }
 
+/// Blame the parties identified by the error for a failed round.
+///
+/// The protocol identifies parties by their position in `signups`.
+fn accuse(round: u16, error: &Error, signups: &[u16]) -> Option<Blame> {
+    let error_type = match error {
+        Error::ProceedRound(ProceedError::Round2VerifyCommitments(e))
+        | Error::ProceedRound(ProceedError::Round3VerifyVssConstruct(e))
+        | Error::ProceedRound(ProceedError::Round4VerifyDLogProof(e)) => e,
+        _ => return None,
+    };
+    Blame::new(round, error_type, error.to_string(), signups)
+}
+
 /// Round-based key share generator.
 #[wasm_bindgen]
 pub struct KeyGenerator {
     inner: Keygen,
+    signups: Vec<u16>,
+    blame: Option<Blame>,
 }
 
 #[wasm_bindgen]
@@ -79,6 +95,8 @@ impl KeyGenerator {
         let (party_num_int, _uuid) = (number, uuid);
         Ok(Self {
             inner: Keygen::new(party_num_int, params.threshold, params.parties)?,
+            signups: (1..=params.parties).collect(),
+            blame: None,
         })
     }
 
@@ -91,14 +109,27 @@ impl KeyGenerator {
     }
 
     /// Proceed to the next round.
+    ///
+    /// When the round fails and the protocol identifies the parties
+    /// responsible the accused parties are available from `blame()`.
     pub fn proceed(&mut self) -> Result<JsValue, JsError> {
-        self.inner.proceed()?;
+        if let Err(e) = self.inner.proceed() {
+            self.blame = accuse(self.inner.current_round(), &e, &self.signups);
+            return Err(e.into());
+        }
         let messages = self.inner.message_queue().drain(..).collect();
         let round = self.inner.current_round();
         let messages = RoundMsg::from_round(round, messages);
         Ok(JsValue::from_serde(&(round, &messages))?)
     }
 
+    /// Get the parties accused of causing the last failed round.
+    ///
+    /// Returns `null` if no parties were identified.
+    pub fn blame(&self) -> Result<JsValue, JsError> {
+        Ok(JsValue::from_serde(&self.blame)?)
+    }
+
     /// Create the key share.
     pub fn create(&mut self) -> Result<JsValue, JsError> {
         let local_key = self.inner.pick_output().unwrap()?;
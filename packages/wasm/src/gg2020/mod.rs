@@ -1,2 +1,101 @@
+use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::ErrorType;
+use serde::{Deserialize, Serialize};
+
 pub mod keygen;
 pub mod sign;
+
+/// Parties accused of causing a protocol round to fail.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Blame {
+    /// Round of the protocol that failed.
+    pub round: u16,
+    /// Kind of error reported by the protocol.
+    pub kind: String,
+    /// Party numbers of the accused parties.
+    pub culprits: Vec<u16>,
+    /// Description of the error.
+    pub message: String,
+}
+
+/// Mirrors the fields of `ErrorType` which are not public.
+#[derive(Deserialize)]
+struct Accusation {
+    error_type: String,
+    bad_actors: Vec<usize>,
+}
+
+impl Blame {
+    /// Create a blame from the error type reported by a failed round.
+    ///
+    /// Bad actors are reported by the protocol as zero-based indices
+    /// of the parties so they are converted to the party signup numbers
+    /// in `signups` which must be in the same order as the parties.
+    pub(crate) fn new(
+        round: u16,
+        error: &ErrorType,
+        message: String,
+        signups: &[u16],
+    ) -> Option<Self> {
+        let value = serde_json::to_value(error).ok()?;
+        let accusation: Accusation = serde_json::from_value(value).ok()?;
+        Self::from_accusation(round, accusation, message, signups)
+    }
+
+    fn from_accusation(
+        round: u16,
+        accusation: Accusation,
+        message: String,
+        signups: &[u16],
+    ) -> Option<Self> {
+        let culprits = accusation
+            .bad_actors
+            .into_iter()
+            .map(|index| signups.get(index).copied())
+            .collect::<Option<Vec<_>>>()?;
+        Some(Self {
+            round,
+            kind: accusation.error_type,
+            culprits,
+            message,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accusation(bad_actors: Vec<usize>) -> Accusation {
+        Accusation {
+            error_type: "bad actor".to_string(),
+            bad_actors,
+        }
+    }
+
+    #[test]
+    fn blame_keygen() {
+        let signups = [1, 2, 3];
+        let blame =
+            Blame::from_accusation(2, accusation(vec![0, 2]), String::new(), &signups).unwrap();
+        assert_eq!(2, blame.round);
+        assert_eq!("bad actor", blame.kind);
+        assert_eq!(vec![1, 3], blame.culprits);
+    }
+
+    #[test]
+    fn blame_sign_signups() {
+        // Signers with key share indices [2, 5, 7] that signed up
+        // to the signing session after party number 2 was freed
+        let signups = [1, 3, 4];
+        let blame =
+            Blame::from_accusation(3, accusation(vec![0, 2]), String::new(), &signups).unwrap();
+        assert_eq!(vec![1, 4], blame.culprits);
+    }
+
+    #[test]
+    fn blame_unknown_party() {
+        let signups = [1, 2];
+        assert!(Blame::from_accusation(1, accusation(vec![2]), String::new(), &signups).is_none());
+    }
+}
@@ -5,8 +5,8 @@ use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::{
     state_machine::{
         keygen::LocalKey,
         sign::{
-            CompletedOfflineStage, OfflineProtocolMessage, OfflineStage, PartialSignature,
-            SignManual,
+            CompletedOfflineStage, Error, OfflineProtocolMessage, OfflineStage, PartialSignature,
+            ProceedError, SignManual,
         },
     },
 };
@@ -16,11 +16,15 @@ use serde::{Deserialize, Serialize};
 use std::convert::TryInto;
 use wasm_bindgen::prelude::*;
 
+use super::Blame;
+
 //use crate::{console_log, log};
 
 const ERR_COMPLETED_OFFLINE_STAGE: &str =
     "completed offline stage unavailable, has partial() been called?";
 
+const ERR_SIGNUPS_LENGTH: &str = "signups must have an entry for each participant";
+
 /// Wrapper for a round `Msg` that includes the round
 /// number so that we can ensure round messages are grouped
 /// together and out of order messages can thus be handled correctly.
@@ -61,28 +65,58 @@ pub struct Signature {
     pub address: String,
 }
 
+/// Blame the parties identified by the error for a failed round.
+///
+/// The protocol identifies parties by their position in the signer set
+/// so they are converted to the party signup numbers in `signups`.
+fn accuse(round: u16, error: &Error, signups: &[u16]) -> Option<Blame> {
+    let error_type = match error {
+        Error::ProceedRound(ProceedError::Round1(e))
+        | Error::ProceedRound(ProceedError::Round2Stage4(e))
+        | Error::ProceedRound(ProceedError::Round3(e))
+        | Error::ProceedRound(ProceedError::Round5(e))
+        | Error::ProceedRound(ProceedError::Round6VerifyProof(e)) => e,
+        _ => return None,
+    };
+    Blame::new(round, error_type, error.to_string(), signups)
+}
+
 /// Round-based signing protocol.
 #[wasm_bindgen]
 pub struct Signer {
     inner: OfflineStage,
+    signups: Vec<u16>,
     completed: Option<(CompletedOfflineStage, BigInt)>,
+    blame: Option<Blame>,
 }
 
 #[wasm_bindgen]
 impl Signer {
     /// Create a signer.
+    ///
+    /// The `participants` are the key share indices of the signers
+    /// and `signups` are the party signup numbers of the signers for
+    /// the session in the same order; both are sorted by party
+    /// signup number.
     #[wasm_bindgen(constructor)]
     pub fn new(
         index: JsValue,
         participants: JsValue,
         local_key: JsValue,
+        signups: JsValue,
     ) -> Result<Signer, JsError> {
         let index: u16 = index.into_serde()?;
         let participants: Vec<u16> = participants.into_serde()?;
         let local_key: LocalKey<Secp256k1> = local_key.into_serde()?;
+        let signups: Vec<u16> = signups.into_serde()?;
+        if signups.len() != participants.len() {
+            return Err(JsError::new(ERR_SIGNUPS_LENGTH));
+        }
         Ok(Signer {
-            inner: OfflineStage::new(index, participants.clone(), local_key)?,
+            inner: OfflineStage::new(index, participants, local_key)?,
+            signups,
             completed: None,
+            blame: None,
         })
     }
 
@@ -95,9 +129,15 @@ impl Signer {
     }
 
     /// Proceed to the next round.
+    ///
+    /// When the round fails and the protocol identifies the parties
+    /// responsible the accused parties are available from `blame()`.
     pub fn proceed(&mut self) -> Result<JsValue, JsError> {
         if self.inner.wants_to_proceed() {
-            self.inner.proceed()?;
+            if let Err(e) = self.inner.proceed() {
+                self.blame = accuse(self.inner.current_round(), &e, &self.signups);
+                return Err(e.into());
+            }
             let messages = self.inner.message_queue().drain(..).collect();
             let round = self.inner.current_round();
             let messages = RoundMsg::from_round(round, messages);
@@ -107,6 +147,13 @@ impl Signer {
         }
     }
 
+    /// Get the parties accused of causing the last failed round.
+    ///
+    /// Returns `null` if no parties were identified.
+    pub fn blame(&self) -> Result<JsValue, JsError> {
+        Ok(JsValue::from_serde(&self.blame)?)
+    }
+
     /// Generate the completed offline stage and store the result
     /// internally to be used when `create()` is called.
     ///
@@ -36,6 +36,7 @@ mod utils;
 // Expose these types for API documentation.
 pub use gg2020::keygen::{KeyGenerator, KeyShare, PartySignup};
 pub use gg2020::sign::{Signature, Signer};
+pub use gg2020::Blame;
 
 /// Parameters used during key generation.
 #[derive(Debug, Clone, Serialize, Deserialize)]
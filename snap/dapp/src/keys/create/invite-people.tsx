@@ -2,6 +2,7 @@ import React, { useState, useEffect, useContext } from "react";
 import { useSelector } from "react-redux";
 
 import { Stack, Typography, CircularProgress } from "@mui/material";
+import { SessionReady } from "@metamask/mpc-client";
 
 import { sessionSelector } from "../../store/session";
 import { WebSocketContext } from "../../websocket-provider";
@@ -17,7 +18,7 @@ export default function InvitePeople(props: StepProps) {
 
   useEffect(() => {
     // All parties signed up to key generation
-    websocket.once("sessionSignup", async (sessionId: string) => {
+    websocket.once("sessionSignup", async ({ sessionId }: SessionReady) => {
       if (sessionId === session.uuid) {
         next();
       } else {
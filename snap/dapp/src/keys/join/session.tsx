@@ -3,7 +3,7 @@ import { useDispatch } from "react-redux";
 
 import { Stack, Typography, CircularProgress } from "@mui/material";
 
-import { SessionKind } from "@metamask/mpc-client";
+import { SessionKind, SessionReady } from "@metamask/mpc-client";
 
 import { WebSocketContext } from "../../websocket-provider";
 import { joinGroupSessionWithSignup } from "../../group-session";
@@ -38,7 +38,7 @@ export default function Compute(props: StepProps) {
       setProgressMessage("Waiting for other participants...");
 
       // All parties signed up to key generation
-      websocket.once("sessionSignup", async (sessionId: string) => {
+      websocket.once("sessionSignup", async ({ sessionId }: SessionReady) => {
         if (sessionId === session.uuid) {
           console.log("Invited participant got session ready...");
           next();
@@ -1,7 +1,7 @@
 import { useContext } from "react";
 import { useSelector, useDispatch } from "react-redux";
 
-import { sign } from "@metamask/mpc-client";
+import { sign, SessionReady } from "@metamask/mpc-client";
 
 import { WebSocketContext } from "../../websocket-provider";
 import { sessionSelector, setSignProof } from "../../store/session";
@@ -21,7 +21,7 @@ export default function Signer(): null {
 
   websocket.removeAllListeners("sessionLoad");
 
-  websocket.once("sessionLoad", async (sessionId: string) => {
+  websocket.once("sessionLoad", async ({ sessionId }: SessionReady) => {
     if (sessionId === session.uuid) {
       const { stream, sink } = transport;
       const { address, value } = signCandidate;